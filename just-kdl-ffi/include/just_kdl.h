@@ -0,0 +1,126 @@
+/* SPDX-License-Identifier: MIT OR Apache-2.0 */
+/*
+ * C bindings for the just-kdl streaming reader, see the `ffi` module docs.
+ *
+ * Strings point into the reader, are valid until the next kdl_reader_next or
+ * kdl_reader_free, and aren't NUL-terminated.
+ *
+ *     KdlReader *reader = kdl_reader_new_buffer(text, strlen(text));
+ *     KdlEvent event;
+ *     KdlStatus status;
+ *     while ((status = kdl_reader_next(reader, &event)) == KDL_STATUS_EVENT) {
+ *         if (event.kind == KDL_EVENT_NODE)
+ *             printf("%.*s\n", (int)event.name.len, (const char *)event.name.ptr);
+ *     }
+ *     if (status == KDL_STATUS_ERROR) {
+ *         KdlError error;
+ *         kdl_reader_error(reader, &error);
+ *         fprintf(stderr, "%zu: %.*s\n", error.span.start,
+ *             (int)error.message.len, (const char *)error.message.ptr);
+ *     }
+ *     kdl_reader_free(reader);
+ */
+#ifndef JUST_KDL_H
+#define JUST_KDL_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Borrowed UTF-8 string, with a NULL ptr if it's missing. */
+typedef struct KdlStr {
+	const uint8_t *ptr;
+	size_t len;
+} KdlStr;
+
+/* Byte range in the input. */
+typedef struct KdlSpan {
+	size_t start;
+	size_t end;
+} KdlSpan;
+
+typedef enum KdlEventKind {
+	/* start of a node, with a name and maybe a type */
+	KDL_EVENT_NODE,
+	/* argument or property, with a value, and maybe a name and type */
+	KDL_EVENT_ENTRY,
+	/* start of the current node's children */
+	KDL_EVENT_CHILDREN,
+	/* end of the current node */
+	KDL_EVENT_END,
+} KdlEventKind;
+
+typedef enum KdlValueKind {
+	/* in text */
+	KDL_VALUE_STRING,
+	/* in text, integer, and number */
+	KDL_VALUE_NUMBER,
+	/* in boolean */
+	KDL_VALUE_BOOL,
+	KDL_VALUE_NULL,
+} KdlValueKind;
+
+typedef struct KdlValue {
+	KdlValueKind kind;
+	/* string contents, or number as written in KDL, like `0x10` or `#inf` */
+	KdlStr text;
+	bool boolean;
+	/* if the number is an integer that fits in integer */
+	bool is_integer;
+	int64_t integer;
+	/* number as a float, infinite if too large */
+	double number;
+} KdlValue;
+
+typedef struct KdlEvent {
+	KdlEventKind kind;
+	/* type annotation of a node or entry */
+	KdlStr type;
+	/* name of a node or property, missing for arguments */
+	KdlStr name;
+	/* value of an entry */
+	KdlValue value;
+	KdlSpan span;
+} KdlEvent;
+
+typedef enum KdlStatus {
+	KDL_STATUS_EVENT,
+	KDL_STATUS_DONE,
+	KDL_STATUS_ERROR,
+} KdlStatus;
+
+typedef struct KdlError {
+	KdlStr message;
+	/* suggestion for fixing the error, if there's a likely one */
+	KdlStr hint;
+	KdlSpan span;
+} KdlError;
+
+/* Reads up to len bytes into buffer, returning how many were read, 0 at
+ * end-of-file, or a negative number on error. */
+typedef intptr_t (*KdlReadFn)(void *user, uint8_t *buffer, size_t len);
+
+typedef struct KdlReader KdlReader;
+
+/* Create a reader of a copy of len bytes at data. */
+KdlReader *kdl_reader_new_buffer(const uint8_t *data, size_t len);
+/* Create a reader that gets its input from read, called with user. */
+KdlReader *kdl_reader_new_callback(KdlReadFn read, void *user);
+/* Read the next event, only written for KDL_STATUS_EVENT. Returns
+ * KDL_STATUS_DONE after the document ends or an error. */
+KdlStatus kdl_reader_next(KdlReader *reader, KdlEvent *event);
+/* Get the last error, returning false if there wasn't one. The message is
+ * valid until the reader is freed. */
+bool kdl_reader_error(const KdlReader *reader, KdlError *error);
+/* Free a reader, along with its strings, doing nothing if it's NULL. */
+void kdl_reader_free(KdlReader *reader);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
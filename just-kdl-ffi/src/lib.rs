@@ -0,0 +1,7 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! C library build of [`just_kdl::ffi`], for `include/just_kdl.h`.
+//!
+//! `cargo build --release -p just-kdl-ffi` builds `libjust_kdl_ffi.so` (or
+//! `.dylib` / `.dll`) and `libjust_kdl_ffi.a` in `target/release`.
+
+pub use just_kdl::ffi::*;
@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Compile `smoke.c` against the header and the static library, checking
+//! the header matches the Rust types.
+#![expect(clippy::tests_outside_test_module, reason = "integration test")]
+
+use std::env::current_exe;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn smoke() {
+	let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+	// target/<profile>/deps/<test> → target/<profile>
+	let exe = current_exe().unwrap();
+	let profile = exe.parent().and_then(Path::parent).unwrap();
+	let binary = Path::new(env!("CARGO_TARGET_TMPDIR")).join("smoke");
+	let compiled = Command::new("cc")
+		.args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-I"])
+		.arg(manifest.join("include"))
+		.arg(manifest.join("tests/smoke.c"))
+		.arg(profile.join("libjust_kdl_ffi.a"))
+		.args(["-lpthread", "-ldl", "-lm", "-o"])
+		.arg(&binary)
+		.status()
+		.unwrap();
+	assert!(compiled.success(), "compile smoke.c");
+	let output = Command::new(&binary).output().unwrap();
+	assert!(output.status.success(), "run smoke.c");
+	assert_eq!(
+		String::from_utf8(output.stdout).unwrap(),
+		concat!(
+			"node 0..7 type=t name=node\n",
+			"entry 8..13 string=a b\n",
+			"entry 14..15 number=1 integer=1 float=1\n",
+			"entry 16..24 name=key number=0x10 integer=16 float=16\n",
+			"entry 25..28 number=1.5 float=1.5\n",
+			"entry 29..34 bool=1\n",
+			"entry 35..40\n",
+			"children 41..42\n",
+			"node 47..52 name=child\n",
+			"end 52..53\n",
+			"end 53..54\n",
+			"node 0..1 name=a\n",
+			"entry 2..3 number=1 integer=1 float=1\n",
+			"end 3..3\n",
+			"node 0..4 name=node\n",
+			"error 5..6 message=Unexpected end-of-file at 6\n",
+		),
+		"events"
+	);
+}
@@ -0,0 +1,75 @@
+/* SPDX-License-Identifier: MIT OR Apache-2.0 */
+/* Reads documents through the C header, printing every event. */
+#include <stdio.h>
+#include <string.h>
+
+#include "just_kdl.h"
+
+static void print_str(const char *label, KdlStr text) {
+	if (text.ptr != NULL)
+		printf(" %s=%.*s", label, (int)text.len, (const char *)text.ptr);
+}
+
+static void print_events(KdlReader *reader) {
+	KdlEvent event;
+	KdlStatus status;
+	while ((status = kdl_reader_next(reader, &event)) == KDL_STATUS_EVENT) {
+		static const char *const kinds[] = {"node", "entry", "children", "end"};
+		printf("%s %zu..%zu", kinds[event.kind], event.span.start, event.span.end);
+		print_str("type", event.type);
+		print_str("name", event.name);
+		switch (event.kind == KDL_EVENT_ENTRY ? event.value.kind : KDL_VALUE_NULL) {
+		case KDL_VALUE_STRING:
+			print_str("string", event.value.text);
+			break;
+		case KDL_VALUE_NUMBER:
+			print_str("number", event.value.text);
+			if (event.value.is_integer)
+				printf(" integer=%lld", (long long)event.value.integer);
+			printf(" float=%g", event.value.number);
+			break;
+		case KDL_VALUE_BOOL:
+			printf(" bool=%d", event.value.boolean);
+			break;
+		case KDL_VALUE_NULL:
+			break;
+		}
+		printf("\n");
+	}
+	if (status == KDL_STATUS_ERROR) {
+		KdlError error;
+		if (kdl_reader_error(reader, &error)) {
+			printf("error %zu..%zu", error.span.start, error.span.end);
+			print_str("message", error.message);
+			printf("\n");
+		}
+	}
+	kdl_reader_free(reader);
+}
+
+struct Cursor {
+	const char *text;
+	size_t left;
+};
+
+/* one byte at a time */
+static intptr_t read_byte(void *user, uint8_t *buffer, size_t len) {
+	struct Cursor *cursor = user;
+	if (cursor->left == 0 || len == 0)
+		return 0;
+	buffer[0] = (uint8_t)*cursor->text;
+	cursor->text++;
+	cursor->left--;
+	return 1;
+}
+
+int main(void) {
+	const char *text = "(t)node \"a b\" 1 key=0x10 1.5 #true #null {\n    child\n}";
+	print_events(kdl_reader_new_buffer((const uint8_t *)text, strlen(text)));
+	struct Cursor cursor = {"a 1", 3};
+	print_events(kdl_reader_new_callback(read_byte, &cursor));
+	const char *broken = "node \"";
+	print_events(kdl_reader_new_buffer((const uint8_t *)broken, strlen(broken)));
+	kdl_reader_free(NULL);
+	return 0;
+}
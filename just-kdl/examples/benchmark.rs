@@ -1,24 +1,33 @@
 //! large-scale benchmark to compare the reference `kdl` with `just-kdl`
 //! for size reasons, the benchmark files aren't provided in this repository,
 //! download them from <https://github.com/kdl-org/kdl/tree/main/tests/benchmarks>
+//! build with `RUSTFLAGS="--cfg just_kdl_scalar"` to time the lexer's scalar
+//! fallback instead of its word-at-a-time scanning
 #![expect(clippy::print_stdout, reason = "binary")]
 
 use std::alloc::{GlobalAlloc, Layout, System};
 use std::env::args;
+use std::fmt::Write as _;
 use std::hint::black_box;
+#[cfg(feature = "std")]
+use std::io::BufReader;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
 use just_kdl::dom::Document;
 #[cfg(feature = "std")]
-use just_kdl::lexer::ReadInput;
+use just_kdl::lexer::{BufReadInput, ReadInput};
+use just_kdl::lexer::{Lexer, Token};
 use just_kdl::reader::Reader;
 
 const HTML_STANDARD: &str = include_str!("html-standard.kdl");
 const HTML_COMPACT: &str = include_str!("html-standard-compact.kdl");
 
 fn main() {
-	let mode = args().nth(1).expect("argv[1] must be {Debug,Release} mode");
+	let mut mode = args().nth(1).expect("argv[1] must be {Debug,Release} mode");
+	if cfg!(just_kdl_scalar) {
+		mode.push_str(" (scalar)");
+	}
 	println!("|Opt.|Parser|Benchmark|Time|Alloc|Resize|Free|Net|");
 	println!("|:-|:-|:-|:-|:-|:-|:-|:-|");
 	print!("|{mode}|`kdl-org/kdl`|`html-standard.kdl`");
@@ -27,12 +36,52 @@ fn main() {
 	run_just_kdl(HTML_STANDARD);
 	print!("|{mode}|`just-kdl` (Read)|`html-standard.kdl`");
 	run_just_kdl_read(HTML_STANDARD);
+	print!("|{mode}|`just-kdl` (BufRead)|`html-standard.kdl`");
+	run_just_kdl_buf_read(HTML_STANDARD);
+	print!("|{mode}|`just-kdl` (Lexer)|`html-standard.kdl`");
+	run_just_kdl_lexer(HTML_STANDARD, false);
+	print!("|{mode}|`just-kdl` (Lexer, skip)|`html-standard.kdl`");
+	run_just_kdl_lexer(HTML_STANDARD, true);
 	print!("|{mode}|`kdl-org/kdl`|`html-standard-compact.kdl`");
 	run_kdl_rs(HTML_COMPACT);
 	print!("|{mode}|`just-kdl`|`html-standard-compact.kdl`");
 	run_just_kdl(HTML_COMPACT);
 	print!("|{mode}|`just-kdl` (Read)|`html-standard-compact.kdl`");
 	run_just_kdl_read(HTML_COMPACT);
+	print!("|{mode}|`just-kdl` (BufRead)|`html-standard-compact.kdl`");
+	run_just_kdl_buf_read(HTML_COMPACT);
+	print!("|{mode}|`just-kdl` (Lexer)|`html-standard-compact.kdl`");
+	run_just_kdl_lexer(HTML_COMPACT, false);
+	print!("|{mode}|`just-kdl` (Lexer, skip)|`html-standard-compact.kdl`");
+	run_just_kdl_lexer(HTML_COMPACT, true);
+	let text = text_heavy();
+	print!("|{mode}|`just-kdl`|text-heavy");
+	run_just_kdl(&text);
+	print!("|{mode}|`just-kdl` (Lexer)|text-heavy");
+	run_just_kdl_lexer(&text, false);
+	print!("|{mode}|`just-kdl` (Lexer, skip)|text-heavy");
+	run_just_kdl_lexer(&text, true);
+}
+
+/// generated document of long strings & comments, where bulk scanning matters
+/// most, included since it doesn't need downloading
+fn text_heavy() -> String {
+	const PROSE: &str = "The quick brown fox jumps over the lazy dog, while the five boxing \
+		wizards jump quickly and a wizard's job is to vex chumps quickly in fog. ";
+	let mut text = String::new();
+	for i in 0..100_000 {
+		text.push_str("// line comments are skipped a word at a time too\n");
+		write!(text, "p id=item-{i} \"").unwrap();
+		for _ in 0..4 {
+			text.push_str(PROSE);
+		}
+		text.push_str("\\n\" #\"raw ");
+		text.push_str(PROSE);
+		text.push_str("\"# /* ");
+		text.push_str(PROSE);
+		text.push_str("*/\n");
+	}
+	text
 }
 
 struct CounterAlloc {
@@ -144,3 +193,28 @@ fn run_just_kdl_read(file: &str) {
 		});
 	}
 }
+
+fn run_just_kdl_buf_read(file: &str) {
+	#[cfg_attr(not(feature = "std"), expect(unused, reason = "feature-gated"))]
+	let file = black_box(file);
+	#[cfg(feature = "std")]
+	{
+		benchmark(|| {
+			Reader::new(BufReadInput::new(BufReader::new(file.as_bytes())))
+				.collect::<Result<Document, _>>()
+				.unwrap()
+		});
+	}
+}
+
+fn run_just_kdl_lexer(file: &str, skip: bool) {
+	let file = black_box(file);
+	benchmark(|| {
+		let mut lexer = Lexer::new(file.as_bytes());
+		let mut tokens = 0_usize;
+		while lexer.next_token(skip).0.unwrap() != Token::Eof {
+			tokens += 1;
+		}
+		tokens
+	});
+}
@@ -0,0 +1,586 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Minimal JSON reading & writing for `kdl convert`.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use just_kdl::dom::number::{Base, Description};
+use just_kdl::dom::{Document, Entry, Event, Node, Number, Value};
+use smol_str::SmolStr;
+
+#[cfg(test)]
+mod tests;
+
+/// Writes an event stream as the JSON form, without building a document.
+pub struct JsonWriter<W> {
+	inner: W,
+	/// per open node, whether a children array was opened
+	stack: Vec<bool>,
+	/// whether the next item in the current array is the first
+	first: bool,
+}
+
+impl<W: Write> JsonWriter<W> {
+	pub fn new(mut inner: W) -> io::Result<Self> {
+		inner.write_all(b"[")?;
+		Ok(Self {
+			inner,
+			stack: Vec::new(),
+			first: true,
+		})
+	}
+	fn separator(&mut self) -> io::Result<()> {
+		if !self.first {
+			self.inner.write_all(b",")?;
+		}
+		self.first = false;
+		Ok(())
+	}
+	pub fn push(&mut self, event: &Event) -> io::Result<()> {
+		match event {
+			Event::Node { r#type, name } => {
+				self.separator()?;
+				self.inner.write_all(b"{\"type\":")?;
+				write_option(&mut self.inner, r#type.as_deref())?;
+				self.inner.write_all(b",\"name\":")?;
+				write_string(&mut self.inner, name)?;
+				self.inner.write_all(b",\"entries\":[")?;
+				self.stack.push(false);
+				self.first = true;
+			}
+			Event::Entry(entry) => {
+				self.separator()?;
+				self.inner.write_all(b"{\"name\":")?;
+				write_option(&mut self.inner, entry.name.as_deref())?;
+				self.inner.write_all(b",\"type\":")?;
+				write_option(&mut self.inner, entry.r#type.as_deref())?;
+				self.inner.write_all(b",\"value\":")?;
+				write_value(&mut self.inner, &entry.value)?;
+				self.inner.write_all(b"}")?;
+			}
+			Event::Children => {
+				self.inner.write_all(b"],\"children\":[")?;
+				if let Some(children) = self.stack.last_mut() {
+					*children = true;
+				}
+				self.first = true;
+			}
+			Event::End => {
+				if self.stack.pop() == Some(true) {
+					self.inner.write_all(b"]}")?;
+				} else {
+					self.inner.write_all(b"],\"children\":null}")?;
+				}
+				self.first = false;
+			}
+		}
+		Ok(())
+	}
+	pub fn finish(mut self) -> io::Result<()> { self.inner.write_all(b"]") }
+}
+
+fn write_option<W: Write>(out: &mut W, text: Option<&str>) -> io::Result<()> {
+	match text {
+		Some(text) => write_string(out, text),
+		None => out.write_all(b"null"),
+	}
+}
+
+fn write_string<W: Write>(out: &mut W, text: &str) -> io::Result<()> {
+	write!(out, "{}", JsonStr(text))
+}
+
+/// Escaped & quoted JSON string.
+pub struct JsonStr<'text>(pub &'text str);
+
+impl fmt::Display for JsonStr<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("\"")?;
+		for ch in self.0.chars() {
+			match ch {
+				'"' => f.write_str("\\\""),
+				'\\' => f.write_str("\\\\"),
+				'\n' => f.write_str("\\n"),
+				'\r' => f.write_str("\\r"),
+				'\t' => f.write_str("\\t"),
+				'\0'..='\x1F' => write!(f, "\\u{:04x}", u32::from(ch)),
+				ch => fmt::Write::write_char(f, ch),
+			}?;
+		}
+		f.write_str("\"")
+	}
+}
+
+fn write_value<W: Write>(out: &mut W, value: &Value) -> io::Result<()> {
+	match value {
+		Value::String(text) => write_string(out, text),
+		Value::Number(number) => write!(out, "{}", JsonNumber(number)),
+		Value::Bool(value) => write!(out, "{value}"),
+		Value::Null => out.write_all(b"null"),
+	}
+}
+
+/// Number in JSON form, special values are written as `{"$kdl": "#inf"}`.
+struct JsonNumber<'number>(&'number Number);
+
+impl fmt::Display for JsonNumber<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.0.describe() {
+			Description::Integer {
+				base: Base::Decimal,
+				text,
+			}
+			| Description::Float { text } => f.write_str(text),
+			// JSON only has decimal numbers
+			Description::Integer { base, text } => {
+				if text.starts_with('-') {
+					f.write_str("-")?;
+				}
+				f.write_str(&decimal_digits(base.as_radix(), text))
+			}
+			Description::Special { text } => write!(f, "{{\"$kdl\":{}}}", JsonStr(text)),
+		}
+	}
+}
+
+/// exact decimal digits of an integer of any size, ignoring its sign
+fn decimal_digits(radix: u32, text: &str) -> String {
+	// little-endian decimal digits
+	let mut decimal = vec![0];
+	for digit in text.chars().filter_map(|ch| ch.to_digit(radix)) {
+		let mut carry = digit;
+		for place in &mut decimal {
+			let value = *place * radix + carry;
+			*place = value % 10;
+			carry = value / 10;
+		}
+		while carry > 0 {
+			decimal.push(carry % 10);
+			carry /= 10;
+		}
+	}
+	decimal
+		.iter()
+		.rev()
+		.map(|&digit| char::from_digit(digit, 10).unwrap_or_else(|| unreachable!()))
+		.collect()
+}
+
+/// special number from its `{"$kdl": "#inf"}` form
+fn special_number(fields: &[(String, Json)]) -> Option<Number> {
+	match fields {
+		[(key, Json::String(text))] if key == "$kdl" => match &**text {
+			"#inf" => Some(Number::INFINITY),
+			"#-inf" => Some(Number::NEG_INFINITY),
+			"#nan" => Some(Number::NAN),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// Parsed JSON value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+	Null,
+	Bool(bool),
+	Number(Number),
+	String(String),
+	Array(Vec<Json>),
+	Object(Vec<(String, Json)>),
+}
+
+impl Json {
+	/// Look up an object field.
+	pub fn get(&self, key: &str) -> Option<&Json> {
+		match self {
+			Json::Object(fields) => fields
+				.iter()
+				.find_map(|(name, value)| (name == key).then_some(value)),
+			_ => None,
+		}
+	}
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Json::String(text) => Some(text),
+			_ => None,
+		}
+	}
+	pub fn as_array(&self) -> Option<&[Json]> {
+		match self {
+			Json::Array(items) => Some(items),
+			_ => None,
+		}
+	}
+}
+
+impl From<&str> for Json {
+	fn from(value: &str) -> Self { Json::String(value.into()) }
+}
+macro_rules! from_int {
+	($($ty:ty),*) => {$(
+		impl From<$ty> for Json {
+			fn from(value: $ty) -> Self { Json::Number(value.into()) }
+		}
+	)*};
+}
+from_int!(i32, u32, usize);
+impl<const N: usize> From<[(&str, Json); N]> for Json {
+	fn from(fields: [(&str, Json); N]) -> Self {
+		Json::Object(
+			fields
+				.into_iter()
+				.map(|(key, value)| (key.into(), value))
+				.collect(),
+		)
+	}
+}
+
+impl fmt::Display for Json {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Json::Null => f.write_str("null"),
+			Json::Bool(value) => write!(f, "{value}"),
+			Json::Number(number) => write!(f, "{}", JsonNumber(number)),
+			Json::String(text) => write!(f, "{}", JsonStr(text)),
+			Json::Array(items) => {
+				f.write_str("[")?;
+				for (i, item) in items.iter().enumerate() {
+					if i > 0 {
+						f.write_str(",")?;
+					}
+					write!(f, "{item}")?;
+				}
+				f.write_str("]")
+			}
+			Json::Object(fields) => {
+				f.write_str("{")?;
+				for (i, (key, value)) in fields.iter().enumerate() {
+					if i > 0 {
+						f.write_str(",")?;
+					}
+					write!(f, "{}:{value}", JsonStr(key))?;
+				}
+				f.write_str("}")
+			}
+		}
+	}
+}
+
+/// Parse any JSON value.
+pub fn parse(text: &[u8]) -> Result<Json, JsonError> {
+	parse_with_starts(text).map(|(value, _)| value)
+}
+
+/// Parse any JSON value, with the start offset of every value in parse order.
+fn parse_with_starts(text: &[u8]) -> Result<(Json, Vec<usize>), JsonError> {
+	let mut parser = Parser {
+		text,
+		pos: 0,
+		starts: Vec::new(),
+	};
+	let value = parser.value()?;
+	if parser.pos < text.len() {
+		return parser.error("trailing characters");
+	}
+	Ok((value, parser.starts))
+}
+
+/// error message and byte offset
+pub type JsonError = (&'static str, usize);
+
+struct Parser<'text> {
+	text: &'text [u8],
+	pos: usize,
+	/// start offset of every value so far
+	starts: Vec<usize>,
+}
+
+impl Parser<'_> {
+	fn error<T>(&self, message: &'static str) -> Result<T, JsonError> { Err((message, self.pos)) }
+	fn spaces(&mut self) {
+		while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.text.get(self.pos) {
+			self.pos += 1;
+		}
+	}
+	fn expect(&mut self, text: &[u8]) -> Result<(), JsonError> {
+		if self.text[self.pos..].starts_with(text) {
+			self.pos += text.len();
+			Ok(())
+		} else {
+			self.error("unexpected character")
+		}
+	}
+	fn value(&mut self) -> Result<Json, JsonError> {
+		self.spaces();
+		self.starts.push(self.pos);
+		let value = match self.text.get(self.pos) {
+			Some(b'n') => self.expect(b"null").map(|()| Json::Null)?,
+			Some(b't') => self.expect(b"true").map(|()| Json::Bool(true))?,
+			Some(b'f') => self.expect(b"false").map(|()| Json::Bool(false))?,
+			Some(b'"') => Json::String(self.string()?),
+			Some(b'[') => {
+				self.pos += 1;
+				let mut items = Vec::new();
+				self.list(b']', |this| {
+					items.push(this.value()?);
+					Ok(())
+				})?;
+				Json::Array(items)
+			}
+			Some(b'{') => {
+				self.pos += 1;
+				let mut items = Vec::new();
+				self.list(b'}', |this| {
+					this.spaces();
+					let key = this.string()?;
+					this.spaces();
+					this.expect(b":")?;
+					items.push((key, this.value()?));
+					Ok(())
+				})?;
+				Json::Object(items)
+			}
+			Some(b'-' | b'0'..=b'9') => {
+				let start = self.pos;
+				while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') =
+					self.text.get(self.pos)
+				{
+					self.pos += 1;
+				}
+				let text = str::from_utf8(&self.text[start..self.pos]).unwrap_or_default();
+				match Number::from_str(text) {
+					Ok(number) => Json::Number(number),
+					Err(()) => return Err(("invalid number", start)),
+				}
+			}
+			Some(_) => return self.error("unexpected character"),
+			None => return self.error("unexpected end of input"),
+		};
+		self.spaces();
+		Ok(value)
+	}
+	/// comma-separated items until `close`
+	fn list(
+		&mut self,
+		close: u8,
+		mut item: impl FnMut(&mut Self) -> Result<(), JsonError>,
+	) -> Result<(), JsonError> {
+		self.spaces();
+		if self.text.get(self.pos) == Some(&close) {
+			self.pos += 1;
+			return Ok(());
+		}
+		loop {
+			item(self)?;
+			match self.text.get(self.pos) {
+				Some(b',') => self.pos += 1,
+				Some(&next) if next == close => {
+					self.pos += 1;
+					return Ok(());
+				}
+				_ => return self.error("expected ',' or end of list"),
+			}
+		}
+	}
+	fn hex4(&mut self) -> Result<u32, JsonError> {
+		let digits = self
+			.text
+			.get(self.pos..self.pos + 4)
+			.and_then(|digits| str::from_utf8(digits).ok())
+			.and_then(|digits| u32::from_str_radix(digits, 16).ok());
+		match digits {
+			Some(value) => {
+				self.pos += 4;
+				Ok(value)
+			}
+			None => self.error("bad unicode escape"),
+		}
+	}
+	fn string(&mut self) -> Result<String, JsonError> {
+		self.expect(b"\"")?;
+		let mut out = Vec::new();
+		loop {
+			let Some(&byte) = self.text.get(self.pos) else {
+				return self.error("unterminated string");
+			};
+			self.pos += 1;
+			match byte {
+				b'"' => break,
+				b'\\' => {
+					let Some(&escape) = self.text.get(self.pos) else {
+						return self.error("unterminated string");
+					};
+					self.pos += 1;
+					let ch = match escape {
+						b'"' => '"',
+						b'\\' => '\\',
+						b'/' => '/',
+						b'b' => '\x08',
+						b'f' => '\x0C',
+						b'n' => '\n',
+						b'r' => '\r',
+						b't' => '\t',
+						b'u' => {
+							let high = self.hex4()?;
+							let code = if (0xD800..0xDC00).contains(&high) {
+								self.expect(b"\\u")?;
+								let low = self.hex4()?;
+								if !(0xDC00..0xE000).contains(&low) {
+									return self.error("bad surrogate pair");
+								}
+								0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+							} else {
+								high
+							};
+							match char::from_u32(code) {
+								Some(ch) => ch,
+								None => return self.error("bad unicode escape"),
+							}
+						}
+						_ => return self.error("bad escape"),
+					};
+					out.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+				}
+				byte => out.push(byte),
+			}
+		}
+		String::from_utf8(out).or_else(|_| self.error("invalid UTF-8"))
+	}
+}
+
+/// Parse a document from its JSON form.
+pub fn parse_document(text: &[u8]) -> Result<Document, JsonError> {
+	let (value, starts) = parse_with_starts(text)?;
+	Convert {
+		starts: &starts,
+		next: 0,
+	}
+	.nodes(value)
+}
+
+/// number of values nested inside `value`
+fn descendants(value: &Json) -> usize {
+	match value {
+		Json::Array(items) => items.iter().map(|item| 1 + descendants(item)).sum(),
+		Json::Object(fields) => fields.iter().map(|(_, item)| 1 + descendants(item)).sum(),
+		_ => 0,
+	}
+}
+
+fn optional_string(value: Json, start: usize) -> Result<Option<SmolStr>, JsonError> {
+	match value {
+		Json::Null => Ok(None),
+		Json::String(text) => Ok(Some(text.into())),
+		_ => Err(("expected string or null", start)),
+	}
+}
+
+/// Document from the JSON form, walking values in the order they were parsed
+/// to find their offsets.
+struct Convert<'starts> {
+	/// start offset of every value, in parse order
+	starts: &'starts [usize],
+	next: usize,
+}
+
+impl Convert<'_> {
+	/// start of the next value, stepping into it
+	fn enter(&mut self) -> usize {
+		let start = self.starts.get(self.next).copied().unwrap_or_default();
+		self.next += 1;
+		start
+	}
+	/// start of the next value, stepping over it
+	fn skip(&mut self, value: &Json) -> usize {
+		let start = self.enter();
+		self.next += descendants(value);
+		start
+	}
+	fn nodes(&mut self, value: Json) -> Result<Document, JsonError> {
+		let start = self.enter();
+		let Json::Array(items) = value else {
+			return Err(("expected array of nodes", start));
+		};
+		items
+			.into_iter()
+			.map(|item| self.node(item))
+			.collect::<Result<Vec<_>, _>>()
+			.map(Document::from)
+	}
+	fn node(&mut self, json: Json) -> Result<Node, JsonError> {
+		let start = self.enter();
+		let Json::Object(fields) = json else {
+			return Err(("expected node object", start));
+		};
+		let mut node = Node::default();
+		let mut has_name = false;
+		for (key, value) in fields {
+			match &*key {
+				"type" => {
+					let at = self.skip(&value);
+					node.r#type = optional_string(value, at)?;
+				}
+				"name" => {
+					let at = self.skip(&value);
+					node.name = optional_string(value, at)?.ok_or(("expected node name", at))?;
+					has_name = true;
+				}
+				"entries" => {
+					let at = self.enter();
+					let Json::Array(items) = value else {
+						return Err(("expected array of entries", at));
+					};
+					node.entries = items
+						.into_iter()
+						.map(|item| self.entry(item))
+						.collect::<Result<_, _>>()?;
+				}
+				"children" => {
+					node.children = match value {
+						Json::Null => {
+							self.enter();
+							None
+						}
+						value => Some(self.nodes(value)?),
+					};
+				}
+				_ => return Err(("unknown node field", self.skip(&value))),
+			}
+		}
+		if has_name {
+			Ok(node)
+		} else {
+			Err(("missing node name", start))
+		}
+	}
+	fn entry(&mut self, json: Json) -> Result<Entry, JsonError> {
+		let start = self.enter();
+		let Json::Object(fields) = json else {
+			return Err(("expected entry object", start));
+		};
+		let mut entry = Entry::new_value(Value::Null);
+		for (key, value) in fields {
+			let at = self.skip(&value);
+			match &*key {
+				"name" => entry.name = optional_string(value, at)?,
+				"type" => entry.r#type = optional_string(value, at)?,
+				"value" => {
+					entry.value = match value {
+						Json::Null => Value::Null,
+						Json::Bool(value) => Value::Bool(value),
+						Json::Number(number) => Value::Number(number),
+						Json::String(text) => Value::String(text.into()),
+						Json::Object(special) => Value::Number(
+							special_number(&special).ok_or(("expected entry value", at))?,
+						),
+						Json::Array(_) => return Err(("expected entry value", at)),
+					}
+				}
+				_ => return Err(("unknown entry field", at)),
+			}
+		}
+		Ok(entry)
+	}
+}
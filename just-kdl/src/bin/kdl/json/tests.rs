@@ -0,0 +1,114 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use just_kdl::dom::Document;
+use just_kdl::reader::Reader;
+
+use super::{Json, JsonWriter, parse, parse_document};
+
+/// `kdl convert json`
+fn to_json(text: &str) -> String {
+	let mut output = Vec::new();
+	let mut writer = JsonWriter::new(&mut output).unwrap();
+	for event in Reader::new(text.as_bytes()) {
+		writer.push(&event.unwrap().0).unwrap();
+	}
+	writer.finish().unwrap();
+	String::from_utf8(output).unwrap()
+}
+
+/// `kdl convert kdl`
+fn to_kdl(json: &str) -> Document { parse_document(json.as_bytes()).unwrap() }
+
+/// JSON values of the first node's entries
+fn values(json: &str) -> Vec<String> {
+	let nodes = parse(json.as_bytes()).unwrap();
+	nodes.as_array().unwrap()[0]
+		.get("entries")
+		.and_then(Json::as_array)
+		.unwrap()
+		.iter()
+		.map(|entry| entry.get("value").unwrap().to_string())
+		.collect()
+}
+
+#[test]
+fn round_trip() {
+	let text = "(t)a 1 -2.5 1.0E+10 \"#inf\" #inf #-inf #nan key=(u)\"v\\n\" #null {\n\tb #true #false\n\tc {\n\t}\n}\nd";
+	let document = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	let json = to_json(text);
+	assert_eq!(to_kdl(&json), document, "kdl → json → kdl");
+	assert_eq!(
+		to_json(&to_kdl(&json).to_string()),
+		json,
+		"json → kdl → json"
+	);
+}
+
+#[test]
+fn numbers() {
+	let json = to_json("n 0x10 -0o17 0b101 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF 1.5E-7");
+	assert_eq!(
+		values(&json),
+		[
+			"16",
+			"-15",
+			"5",
+			"22300745198530623141535718272648361505980415",
+			"1.5E-7"
+		],
+		"exact decimal"
+	);
+	assert_eq!(to_json(&to_kdl(&json).to_string()), json, "round trip");
+}
+
+#[test]
+fn specials() {
+	let json = to_json("n #inf #-inf #nan \"#inf\"");
+	assert_eq!(
+		values(&json),
+		[
+			r##"{"$kdl":"#inf"}"##,
+			r##"{"$kdl":"#-inf"}"##,
+			r##"{"$kdl":"#nan"}"##,
+			r##""#inf""##
+		],
+		"unambiguous"
+	);
+	assert!(
+		parse_document(br#"[{"name":"n","entries":[{"value":{"$kdl":"inf"}}]}]"#).is_err(),
+		"unknown special"
+	);
+}
+
+#[test]
+fn error_offsets() {
+	let error = |json: &str| parse_document(json.as_bytes()).unwrap_err();
+	assert_eq!(error(" {}"), ("expected array of nodes", 1), "root");
+	assert_eq!(
+		error("[{\"name\":\"a\"}, 1]"),
+		("expected node object", 15),
+		"node"
+	);
+	assert_eq!(
+		error("[{\"name\":null}]"),
+		("expected node name", 9),
+		"name"
+	);
+	assert_eq!(
+		error("[{\"type\":\"t\"}]"),
+		("missing node name", 1),
+		"missing name"
+	);
+	assert_eq!(
+		error(r#"[{"name":"a","entries":[{"value":1},{"value":[]}]}]"#),
+		("expected entry value", 45),
+		"entry value"
+	);
+	let json = r##"[{"name":"a","children":[{"name":"b","children":null}],"entries":[{"value":{"$kdl":"#inf"}},{"k":0}]}]"##;
+	assert_eq!(
+		error(json),
+		("unknown entry field", json.find('0').unwrap()),
+		"after skipped values"
+	);
+}
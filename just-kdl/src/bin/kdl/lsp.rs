@@ -0,0 +1,486 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Language server over stdio, for `kdl lsp`.
+//!
+//! Documents are synced in full on every change, and positions use the
+//! default UTF-16 encoding. Diagnostics only cover the first syntax error,
+//! since the reader doesn't recover from errors to find any later ones.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, ErrorKind, Write};
+use std::ops::Range;
+
+use just_kdl::dom::{Document, Event};
+use just_kdl::highlight::{Highlight, highlight};
+use just_kdl::reader::Reader;
+use just_kdl::writer::Writer;
+
+use crate::json::{Json, parse};
+
+#[cfg(test)]
+mod tests;
+
+/// semantic token legend, indexed by [`token_type`]
+const TOKEN_TYPES: [&str; 8] = [
+	"comment", "class", "type", "property", "string", "number", "keyword", "operator",
+];
+
+fn token_type(class: Highlight) -> Option<usize> {
+	Some(match class {
+		Highlight::Comment | Highlight::SlashDash => 0,
+		Highlight::NodeName => 1,
+		Highlight::Type => 2,
+		Highlight::PropertyKey => 3,
+		Highlight::String => 4,
+		Highlight::Number => 5,
+		Highlight::Keyword => 6,
+		Highlight::Punctuation => 7,
+		_ => return None,
+	})
+}
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i32 = -32700;
+const METHOD_NOT_FOUND: i32 = -32601;
+const INVALID_PARAMS: i32 = -32602;
+
+/// Run the server until an `exit` notification or end of input.
+/// Returns whether a `shutdown` request was received first.
+///
+/// # Errors
+/// On IO or framing errors.
+pub fn run<R: BufRead, W: Write>(mut input: R, output: W) -> io::Result<bool> {
+	let mut server = Server {
+		output,
+		documents: HashMap::new(),
+		shutdown: false,
+	};
+	while let Some(body) = read_message(&mut input)? {
+		let Ok(message) = parse(&body) else {
+			// there's no id to respond to, so it's null
+			server.send(&error_response(Json::Null, PARSE_ERROR, "parse error"))?;
+			continue;
+		};
+		if !server.handle(&message)? {
+			break;
+		}
+	}
+	Ok(server.shutdown)
+}
+
+fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Vec<u8>>> {
+	let mut length = None;
+	let mut header = String::new();
+	loop {
+		header.clear();
+		if input.read_line(&mut header)? == 0 {
+			return Ok(None);
+		}
+		let trimmed = header.trim_end();
+		if trimmed.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = trimmed.split_once(':')
+			&& name.eq_ignore_ascii_case("Content-Length")
+		{
+			length = value.trim().parse::<usize>().ok();
+		}
+	}
+	let length =
+		length.ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "missing Content-Length"))?;
+	let mut body = vec![0; length];
+	input.read_exact(&mut body)?;
+	Ok(Some(body))
+}
+
+struct Server<W> {
+	output: W,
+	/// uri → text
+	documents: HashMap<String, String>,
+	shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+	fn send(&mut self, message: &Json) -> io::Result<()> {
+		let body = message.to_string();
+		write!(self.output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+		self.output.flush()
+	}
+	/// returns false to exit
+	fn handle(&mut self, message: &Json) -> io::Result<bool> {
+		let Some(method) = message.get("method").and_then(Json::as_str) else {
+			// responses to server requests, which are never sent
+			return Ok(true);
+		};
+		let params = message.get("params").unwrap_or(&Json::Null);
+		let Some(id) = message.get("id") else {
+			return self.notification(method, params);
+		};
+		let response = match self.request(method, params) {
+			Ok(result) => Json::from([
+				("jsonrpc", "2.0".into()),
+				("id", id.clone()),
+				("result", result),
+			]),
+			Err((code, text)) => error_response(id.clone(), code, text),
+		};
+		self.send(&response)?;
+		Ok(true)
+	}
+	fn notification(&mut self, method: &str, params: &Json) -> io::Result<bool> {
+		let uri = params
+			.get("textDocument")
+			.and_then(|doc| doc.get("uri"))
+			.and_then(Json::as_str);
+		match (method, uri) {
+			("exit", _) => return Ok(false),
+			("textDocument/didOpen", Some(uri)) => {
+				let text = params
+					.get("textDocument")
+					.and_then(|doc| doc.get("text"))
+					.and_then(Json::as_str)
+					.unwrap_or_default();
+				self.documents.insert(uri.into(), text.into());
+				self.publish(uri)?;
+			}
+			("textDocument/didChange", Some(uri)) => {
+				// full sync, so the last change is the whole document
+				let text = params
+					.get("contentChanges")
+					.and_then(Json::as_array)
+					.and_then(<[Json]>::last)
+					.and_then(|change| change.get("text"))
+					.and_then(Json::as_str);
+				if let Some(text) = text {
+					self.documents.insert(uri.into(), text.into());
+					self.publish(uri)?;
+				}
+			}
+			("textDocument/didClose", Some(uri)) => {
+				self.documents.remove(uri);
+				self.send(&publish_diagnostics(uri, Json::Array(Vec::new())))?;
+			}
+			_ => {}
+		}
+		Ok(true)
+	}
+	fn publish(&mut self, uri: &str) -> io::Result<()> {
+		let diagnostics = self
+			.documents
+			.get(uri)
+			.map_or(Json::Null, |text| diagnostics(text));
+		self.send(&publish_diagnostics(uri, diagnostics))
+	}
+	fn request(&mut self, method: &str, params: &Json) -> Result<Json, (i32, &'static str)> {
+		if method == "initialize" {
+			return Ok(capabilities());
+		} else if method == "shutdown" {
+			self.shutdown = true;
+			return Ok(Json::Null);
+		}
+		let text = params
+			.get("textDocument")
+			.and_then(|doc| doc.get("uri"))
+			.and_then(Json::as_str)
+			.and_then(|uri| self.documents.get(uri));
+		let text = match (method, text) {
+			(
+				"textDocument/formatting"
+				| "textDocument/documentSymbol"
+				| "textDocument/semanticTokens/full"
+				| "textDocument/foldingRange",
+				Some(text),
+			) => text,
+			(
+				"textDocument/formatting"
+				| "textDocument/documentSymbol"
+				| "textDocument/semanticTokens/full"
+				| "textDocument/foldingRange",
+				None,
+			) => return Err((INVALID_PARAMS, "unknown document")),
+			_ => return Err((METHOD_NOT_FOUND, "method not found")),
+		};
+		Ok(match method {
+			"textDocument/formatting" => formatting(text, params.get("options")),
+			"textDocument/documentSymbol" => document_symbols(text),
+			"textDocument/semanticTokens/full" => semantic_tokens(text),
+			_ => folding_ranges(text),
+		})
+	}
+}
+
+fn error_response(id: Json, code: i32, text: &str) -> Json {
+	Json::from([
+		("jsonrpc", "2.0".into()),
+		("id", id),
+		(
+			"error",
+			Json::from([("code", code.into()), ("message", text.into())]),
+		),
+	])
+}
+
+fn capabilities() -> Json {
+	let legend = Json::from([
+		(
+			"tokenTypes",
+			Json::Array(TOKEN_TYPES.iter().map(|&name| name.into()).collect()),
+		),
+		("tokenModifiers", Json::Array(Vec::new())),
+	]);
+	Json::from([
+		(
+			"capabilities",
+			Json::from([
+				// full sync
+				("textDocumentSync", 1_u32.into()),
+				("documentFormattingProvider", Json::Bool(true)),
+				("documentSymbolProvider", Json::Bool(true)),
+				("foldingRangeProvider", Json::Bool(true)),
+				(
+					"semanticTokensProvider",
+					Json::from([("legend", legend), ("full", Json::Bool(true))]),
+				),
+			]),
+		),
+		("serverInfo", Json::from([("name", "just-kdl".into())])),
+	])
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Json) -> Json {
+	Json::from([
+		("jsonrpc", "2.0".into()),
+		("method", "textDocument/publishDiagnostics".into()),
+		(
+			"params",
+			Json::from([("uri", uri.into()), ("diagnostics", diagnostics)]),
+		),
+	])
+}
+
+/// Byte offset to LSP position mapping.
+struct Lines<'text> {
+	text: &'text str,
+	/// start offset of each line
+	starts: Vec<usize>,
+}
+
+impl<'text> Lines<'text> {
+	fn new(text: &'text str) -> Self {
+		let bytes = text.as_bytes();
+		let mut starts = vec![0];
+		for (i, &byte) in bytes.iter().enumerate() {
+			// LSP only recognizes \n, \r\n, and \r
+			if byte == b'\n' || (byte == b'\r' && bytes.get(i + 1) != Some(&b'\n')) {
+				starts.push(i + 1);
+			}
+		}
+		Self { text, starts }
+	}
+	fn line(&self, offset: usize) -> usize {
+		self.starts.partition_point(|&start| start <= offset) - 1
+	}
+	/// length in UTF-16 code units
+	fn utf16_len(&self, span: Range<usize>) -> usize {
+		self.text
+			.get(span)
+			.map_or(0, |text| text.encode_utf16().count())
+	}
+	/// (line, utf-16 character)
+	fn position(&self, offset: usize) -> (usize, usize) {
+		let offset = offset.min(self.text.len());
+		let line = self.line(offset);
+		(line, self.utf16_len(self.starts[line]..offset))
+	}
+	fn position_json(&self, offset: usize) -> Json {
+		let (line, character) = self.position(offset);
+		Json::from([("line", line.into()), ("character", character.into())])
+	}
+	fn range(&self, span: Range<usize>) -> Json {
+		Json::from([
+			("start", self.position_json(span.start)),
+			("end", self.position_json(span.end)),
+		])
+	}
+}
+
+/// the first syntax error, if any
+fn diagnostics(text: &str) -> Json {
+	let lines = Lines::new(text);
+	Json::Array(
+		Reader::new(text.as_bytes())
+			.find_map(Result::err)
+			.map(|(err, span)| {
+				let mut message = err.to_string();
+				if let Some(hint) = err.hint() {
+					message = format!("{message}\nhint: {hint}");
+				}
+				Json::from([
+					("range", lines.range(span)),
+					// error
+					("severity", 1_u32.into()),
+					("source", "just-kdl".into()),
+					("message", message.as_str().into()),
+				])
+			})
+			.into_iter()
+			.collect(),
+	)
+}
+
+fn formatting(text: &str, options: Option<&Json>) -> Json {
+	let Ok(document) = Reader::new(text.as_bytes()).collect::<Result<Document, _>>() else {
+		// can't format invalid documents
+		return Json::Null;
+	};
+	let insert_spaces = options.and_then(|options| options.get("insertSpaces"));
+	let tab_size = options
+		.and_then(|options| options.get("tabSize"))
+		.and_then(|size| match size {
+			Json::Number(size) => u8::try_from(size).ok(),
+			_ => None,
+		});
+	let indent = match (insert_spaces, tab_size) {
+		(Some(Json::Bool(false)), _) => "\t".into(),
+		(_, Some(size)) => " ".repeat(size.into()),
+		_ => "    ".into(),
+	};
+	let mut formatted = String::new();
+	let mut writer = Writer::new(&mut formatted);
+	writer.set_indent(indent);
+	for event in &document {
+		// writing to a string can't fail
+		_ = writer.push(&event);
+	}
+	if !formatted.is_empty() {
+		formatted.push('\n');
+	}
+	if formatted == text {
+		return Json::Array(Vec::new());
+	}
+	let lines = Lines::new(text);
+	Json::Array(vec![Json::from([
+		("range", lines.range(0..text.len())),
+		("newText", formatted.as_str().into()),
+	])])
+}
+
+fn document_symbols(text: &str) -> Json {
+	struct Symbol {
+		name: String,
+		detail: Option<String>,
+		start: usize,
+		selection: Range<usize>,
+		children: Vec<Json>,
+	}
+	let lines = Lines::new(text);
+	let mut stack = Vec::<Symbol>::new();
+	let mut roots = Vec::new();
+	for (event, span) in Reader::new(text.as_bytes()).map_while(Result::ok) {
+		match event {
+			Event::Node { r#type, name } => stack.push(Symbol {
+				// editors reject empty names
+				name: if name.is_empty() {
+					"\"\"".into()
+				} else {
+					name.into()
+				},
+				detail: r#type.map(Into::into),
+				start: span.start,
+				selection: span,
+				children: Vec::new(),
+			}),
+			Event::End => {
+				let Some(symbol) = stack.pop() else {
+					continue;
+				};
+				let json = Json::from([
+					("name", symbol.name.as_str().into()),
+					(
+						"detail",
+						symbol.detail.as_deref().map_or(Json::Null, Into::into),
+					),
+					// object
+					("kind", 19_u32.into()),
+					("range", lines.range(symbol.start..span.end)),
+					("selectionRange", lines.range(symbol.selection)),
+					("children", Json::Array(symbol.children)),
+				]);
+				match stack.last_mut() {
+					Some(parent) => parent.children.push(json),
+					None => roots.push(json),
+				}
+			}
+			Event::Entry(_) | Event::Children => {}
+		}
+	}
+	Json::Array(roots)
+}
+
+fn semantic_tokens(text: &str) -> Json {
+	let lines = Lines::new(text);
+	let mut data = Vec::new();
+	let mut prev = (0, 0);
+	for (class, span) in highlight(text.as_bytes()) {
+		let Some(kind) = token_type(class) else {
+			continue;
+		};
+		// tokens can't span multiple lines
+		let mut start = span.start;
+		while start < span.end {
+			let line_end = lines
+				.starts
+				.get(lines.line(start) + 1)
+				.copied()
+				.unwrap_or(text.len())
+				.min(span.end);
+			let mut end = line_end;
+			while end > start && matches!(text.as_bytes()[end - 1], b'\n' | b'\r') {
+				end -= 1;
+			}
+			let length = lines.utf16_len(start..end);
+			if length > 0 {
+				let (line, character) = lines.position(start);
+				let delta_start = if line == prev.0 {
+					character - prev.1
+				} else {
+					character
+				};
+				data.extend([line - prev.0, delta_start, length, kind, 0].map(Json::from));
+				prev = (line, character);
+			}
+			start = line_end;
+		}
+	}
+	Json::from([("data", Json::Array(data))])
+}
+
+fn folding_ranges(text: &str) -> Json {
+	let lines = Lines::new(text);
+	// children block start line for each open node
+	let mut stack = Vec::<Option<usize>>::new();
+	let mut ranges = Vec::new();
+	for (event, span) in Reader::new(text.as_bytes()).map_while(Result::ok) {
+		match event {
+			Event::Node { .. } => stack.push(None),
+			Event::Children => {
+				if let Some(top) = stack.last_mut() {
+					*top = Some(lines.line(span.start));
+				}
+			}
+			Event::End => {
+				if let Some(Some(start)) = stack.pop() {
+					// keep the closing brace visible
+					let end = lines.line(span.start).saturating_sub(1);
+					if end > start {
+						ranges.push(Json::from([
+							("startLine", start.into()),
+							("endLine", end.into()),
+						]));
+					}
+				}
+			}
+			Event::Entry(_) => {}
+		}
+	}
+	Json::Array(ranges)
+}
@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+use super::run;
+use crate::json::{Json, parse};
+
+fn session(messages: &[&str]) -> (bool, Vec<Json>) {
+	let mut input = Vec::new();
+	for message in messages {
+		input.extend(format!("Content-Length: {}\r\n\r\n{message}", message.len()).bytes());
+	}
+	let mut output = Vec::new();
+	let clean = run(&*input, &mut output).unwrap();
+	let mut rest = &*output;
+	let mut responses = Vec::new();
+	while let Some(split) = rest.windows(4).position(|window| window == b"\r\n\r\n") {
+		let header = str::from_utf8(&rest[..split]).unwrap();
+		let length = header["Content-Length: ".len()..].parse::<usize>().unwrap();
+		let body = &rest[split + 4..split + 4 + length];
+		responses.push(parse(body).unwrap());
+		rest = &rest[split + 4 + length..];
+	}
+	(clean, responses)
+}
+
+fn result(response: &Json) -> String { response.get("result").unwrap().to_string() }
+
+#[test]
+fn lifecycle() {
+	let (clean, responses) = session(&[
+		r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+		r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#,
+		r#"{"jsonrpc":"2.0","id":2,"method":"unknown","params":{}}"#,
+		r#"{"jsonrpc":"2.0","id":5,"#,
+		r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+		r#"{"jsonrpc":"2.0","method":"exit"}"#,
+		r#"{"jsonrpc":"2.0","id":4,"method":"shutdown"}"#,
+	]);
+	assert!(clean, "shutdown before exit");
+	assert_eq!(responses.len(), 4, "responses stop after exit");
+	assert!(
+		result(&responses[0]).contains(r#""textDocumentSync":1"#),
+		"capabilities"
+	);
+	assert_eq!(
+		responses[1].get("error").unwrap().to_string(),
+		r#"{"code":-32601,"message":"method not found"}"#,
+		"unknown method"
+	);
+	assert_eq!(
+		responses[2].to_string(),
+		r#"{"jsonrpc":"2.0","id":null,"error":{"code":-32700,"message":"parse error"}}"#,
+		"malformed message"
+	);
+	assert_eq!(result(&responses[3]), "null", "shutdown");
+	let (abrupt, _) = session(&[r#"{"jsonrpc":"2.0","method":"exit"}"#]);
+	assert!(!abrupt, "exit without shutdown");
+}
+
+#[test]
+fn documents() {
+	let (_, responses) = session(&[
+		r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"a","text":"é (t)a 1 {\n  b\n  c\n}\nd \"\n"}}}"#,
+		r#"{"jsonrpc":"2.0","method":"textDocument/didChange","params":{"textDocument":{"uri":"a"},"contentChanges":[{"text":"(t)a  1 {\n  b; c\n\n}\n"}]}}"#,
+		r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/documentSymbol","params":{"textDocument":{"uri":"a"}}}"#,
+		r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/foldingRange","params":{"textDocument":{"uri":"a"}}}"#,
+		r#"{"jsonrpc":"2.0","id":3,"method":"textDocument/formatting","params":{"textDocument":{"uri":"a"},"options":{"tabSize":2,"insertSpaces":true}}}"#,
+		r#"{"jsonrpc":"2.0","id":4,"method":"textDocument/semanticTokens/full","params":{"textDocument":{"uri":"a"}}}"#,
+		r#"{"jsonrpc":"2.0","method":"textDocument/didClose","params":{"textDocument":{"uri":"a"}}}"#,
+		r#"{"jsonrpc":"2.0","id":5,"method":"textDocument/formatting","params":{"textDocument":{"uri":"a"}}}"#,
+	]);
+	let diagnostics = |response: &Json| {
+		response
+			.get("params")
+			.unwrap()
+			.get("diagnostics")
+			.unwrap()
+			.to_string()
+	};
+	let opened = diagnostics(&responses[0]);
+	assert!(
+		opened.contains(r#""range":{"start":{"line":4,"character":2},"#),
+		"unclosed string: {opened}"
+	);
+	assert_eq!(diagnostics(&responses[1]), "[]", "fixed");
+	let symbols = result(&responses[2]);
+	assert!(
+		symbols.starts_with(r#"[{"name":"a","detail":"t","kind":19,"range":{"start":{"line":0,"character":0},"end":{"line":3,"character":1}}"#),
+		"symbols: {symbols}"
+	);
+	assert!(
+		symbols.contains(r#""name":"c""#),
+		"nested symbols: {symbols}"
+	);
+	assert_eq!(
+		result(&responses[3]),
+		r#"[{"startLine":0,"endLine":2}]"#,
+		"folding"
+	);
+	assert_eq!(
+		result(&responses[4]),
+		r#"[{"range":{"start":{"line":0,"character":0},"end":{"line":4,"character":0}},"newText":"(t)a 1 {\n  b\n  c\n}\n"}]"#,
+		"formatting"
+	);
+	let tokens = result(&responses[5]);
+	// `(`, `t`, `)`, `a`, `1`, `{`
+	assert!(
+		tokens
+			.starts_with(r#"{"data":[0,0,1,7,0,0,1,1,2,0,0,1,1,7,0,0,1,1,1,0,0,3,1,5,0,0,2,1,7,0"#),
+		"tokens: {tokens}"
+	);
+	assert_eq!(diagnostics(&responses[6]), "[]", "closed");
+	assert_eq!(
+		responses[7]
+			.get("error")
+			.unwrap()
+			.get("code")
+			.unwrap()
+			.to_string(),
+		"-32602",
+		"closed document"
+	);
+}
+
+#[test]
+fn formatting_options() {
+	let (_, responses) = session(&[
+		r#"{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{"textDocument":{"uri":"a","text":"a {\n b\n}\n"}}}"#,
+		r#"{"jsonrpc":"2.0","id":1,"method":"textDocument/formatting","params":{"textDocument":{"uri":"a"},"options":{"tabSize":3,"insertSpaces":true}}}"#,
+		r#"{"jsonrpc":"2.0","id":2,"method":"textDocument/formatting","params":{"textDocument":{"uri":"a"},"options":{"tabSize":3,"insertSpaces":false}}}"#,
+	]);
+	let new_text = |response: &Json| {
+		response.get("result").unwrap().as_array().unwrap()[0]
+			.get("newText")
+			.unwrap()
+			.to_string()
+	};
+	assert_eq!(new_text(&responses[1]), r#""a {\n   b\n}\n""#, "tab size");
+	assert_eq!(new_text(&responses[2]), r#""a {\n\tb\n}\n""#, "tabs");
+}
@@ -0,0 +1,356 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! `kdl` command-line tool, a small wrapper around the library.
+//!
+//! Run `kdl help` for usage.
+#![expect(clippy::print_stdout, clippy::print_stderr, reason = "binary")]
+
+use std::env::args;
+use std::fs::{File, read, write};
+use std::io::{self, BufReader, BufWriter, Read, Write, stdin, stdout};
+use std::process::ExitCode;
+
+use just_kdl::dom::iter::DocumentBuilder;
+use just_kdl::dom::{Document, Event};
+use just_kdl::lexer::{BufReadInput, LexerError};
+use just_kdl::reader::{Reader, ReaderError, Spanned};
+use just_kdl::writer::{IoWriter, WriterError};
+
+mod json;
+mod lsp;
+
+const HELP: &str = "\
+Usage: kdl <command> [options] [file...]
+
+Reads from standard input when no file (or `-`) is given.
+
+Commands:
+  fmt [--check] [file...]   Reformat files in place (or stdin to stdout).
+                            Comments and formatting are not preserved.
+                            --check: only report files that would change.
+  check [file...]           Report syntax errors.
+  events [file]             Print the event stream with spans.
+  convert <to> [file]       Convert between formats, <to> is one of:
+                            json: KDL to JSON
+                            kdl: JSON to KDL
+  query <path> [file]       Print nodes matching a path, e.g. `a/*/c`,
+                            where `*` matches any node name.
+  lsp                       Run a language server over stdio.
+  help                      Print this message.
+
+JSON form is an array of nodes, each node is
+  {\"type\": string?, \"name\": string, \"entries\": [entry], \"children\": [node]?}
+and each entry is
+  {\"name\": string?, \"type\": string?, \"value\": string | number | bool | null}
+with integers written in decimal, and #inf, #-inf, and #nan written as
+  {\"$kdl\": \"#inf\"}, {\"$kdl\": \"#-inf\"}, and {\"$kdl\": \"#nan\"}.
+
+Exit status is 0 on success, 1 on syntax errors or formatting changes,
+and 2 on usage or IO errors.";
+
+/// Process failure reason.
+enum Failure {
+	/// Syntax error / check failure, already reported.
+	Reported,
+	/// Usage error.
+	Usage(String),
+	/// IO error.
+	Io(String, io::Error),
+}
+
+type CliResult<T = ()> = Result<T, Failure>;
+
+fn main() -> ExitCode {
+	let args = args().skip(1).collect::<Vec<_>>();
+	let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+	let result = match args.as_slice() {
+		["fmt", rest @ ..] => fmt(rest),
+		["check", files @ ..] => each_file(files, check),
+		["events", rest @ ..] => single_file(rest).and_then(events),
+		["convert", to, rest @ ..] => single_file(rest).and_then(|file| convert(to, file)),
+		["query", path, rest @ ..] => single_file(rest).and_then(|file| query(path, file)),
+		["lsp"] => lsp(),
+		["help" | "--help" | "-h"] => {
+			println!("{HELP}");
+			Ok(())
+		}
+		_ => Err(Failure::Usage("expected a command".into())),
+	};
+	match result {
+		Ok(()) => ExitCode::SUCCESS,
+		Err(Failure::Reported) => ExitCode::from(1),
+		Err(Failure::Usage(message)) => {
+			eprintln!("kdl: {message}\n\n{HELP}");
+			ExitCode::from(2)
+		}
+		Err(Failure::Io(file, err)) => {
+			eprintln!("kdl: {file}: {err}");
+			ExitCode::from(2)
+		}
+	}
+}
+
+/// `-` for standard input
+fn file_name(file: Option<&str>) -> &str { file.unwrap_or("-") }
+
+fn single_file<'arg>(rest: &[&'arg str]) -> CliResult<Option<&'arg str>> {
+	match rest {
+		[] => Ok(None),
+		[file] => Ok(Some(file)),
+		_ => Err(Failure::Usage("too many arguments".into())),
+	}
+}
+
+/// run for each file, continuing after errors
+fn each_file(files: &[&str], f: fn(Option<&str>) -> CliResult) -> CliResult {
+	if files.is_empty() {
+		return f(None);
+	}
+	let mut result = Ok(());
+	for &file in files {
+		match f(Some(file)) {
+			Ok(()) => {}
+			Err(Failure::Reported) => result = Err(Failure::Reported),
+			Err(err) => return Err(err),
+		}
+	}
+	result
+}
+
+fn open(file: Option<&str>) -> CliResult<Box<dyn Read>> {
+	match file {
+		None | Some("-") => Ok(Box::new(stdin().lock())),
+		Some(path) => File::open(path)
+			.map(|handle| Box::new(handle) as Box<dyn Read>)
+			.map_err(|err| Failure::Io(path.into(), err)),
+	}
+}
+
+fn read_all(file: Option<&str>) -> CliResult<Vec<u8>> {
+	let mut out = Vec::new();
+	match file {
+		None | Some("-") => stdin()
+			.read_to_end(&mut out)
+			.map(|_| out)
+			.map_err(|err| Failure::Io("-".into(), err)),
+		Some(path) => read(path).map_err(|err| Failure::Io(path.into(), err)),
+	}
+}
+
+fn output_error(err: io::Error) -> Failure { Failure::Io("<stdout>".into(), err) }
+
+fn writer_error(err: WriterError) -> Failure {
+	match err {
+		WriterError::Io(err) => output_error(err),
+		err => output_error(io::Error::other(err)),
+	}
+}
+
+/// Tracks line starts of everything read through it, for diagnostics.
+struct LineIndex<R> {
+	inner: R,
+	offset: usize,
+	/// starting offset of every line after the first
+	lines: Vec<usize>,
+}
+
+impl<R> LineIndex<R> {
+	fn new(inner: R) -> Self {
+		Self {
+			inner,
+			offset: 0,
+			lines: Vec::new(),
+		}
+	}
+	fn scan(&mut self, bytes: &[u8]) {
+		for (i, &byte) in bytes.iter().enumerate() {
+			if byte == b'\n' {
+				self.lines.push(self.offset + i + 1);
+			}
+		}
+		self.offset += bytes.len();
+	}
+	/// 1-based line & byte column
+	fn position(&self, offset: usize) -> (usize, usize) {
+		let line = self.lines.partition_point(|&start| start <= offset);
+		let start = line.checked_sub(1).map_or(0, |i| self.lines[i]);
+		(line + 1, offset - start + 1)
+	}
+}
+
+impl<R: Read> Read for LineIndex<R> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let size = self.inner.read(buf)?;
+		self.scan(&buf[..size]);
+		Ok(size)
+	}
+}
+
+/// print a reader error, mapping IO errors
+fn report<R>(
+	file: Option<&str>,
+	index: &LineIndex<R>,
+	(err, span): Spanned<ReaderError>,
+) -> Failure {
+	let name = file_name(file);
+	if let ReaderError::Lexer(LexerError::Io(err)) = err {
+		return Failure::Io(name.into(), err);
+	}
+	let (line, column) = index.position(span.start);
+	eprintln!("{name}:{line}:{column}: {err}");
+	if let ReaderError::UnclosedOpen(open) = err {
+		let (open_line, open_column) = index.position(open);
+		eprintln!("{name}:{open_line}:{open_column}: note: opened here");
+	}
+	if let Some(hint) = err.hint() {
+		eprintln!("  hint: {hint}");
+	}
+	Failure::Reported
+}
+
+/// Stream events from a file, reporting any errors.
+fn stream(file: Option<&str>, mut f: impl FnMut(Spanned<Event>) -> CliResult) -> CliResult {
+	let mut index = LineIndex::new(open(file)?);
+	let mut reader = Reader::new(BufReadInput::new(BufReader::new(&mut index)));
+	let error = loop {
+		match reader.next() {
+			None => return Ok(()),
+			Some(Ok(event)) => f(event)?,
+			Some(Err(err)) => break err,
+		}
+	};
+	drop(reader);
+	Err(report(file, &index, error))
+}
+
+fn fmt(rest: &[&str]) -> CliResult {
+	let (check, files) = match rest {
+		["--check", files @ ..] => (true, files),
+		files => (false, files),
+	};
+	if files.is_empty() || files == ["-"] {
+		if check {
+			return fmt_file(None, true);
+		}
+		// streaming stdin, but buffering the output so a syntax error doesn't
+		// leave half a document on stdout
+		let mut writer = IoWriter::new(Vec::new());
+		let mut any = false;
+		stream(None, |(event, _)| {
+			any = true;
+			writer.push(&event).map_err(writer_error)
+		})?;
+		let mut output = writer.finish().map_err(writer_error)?;
+		if any {
+			output.push(b'\n');
+		}
+		let mut stdout = stdout().lock();
+		stdout.write_all(&output).map_err(output_error)?;
+		return stdout.flush().map_err(output_error);
+	}
+	let fmt_one: fn(Option<&str>) -> CliResult = if check {
+		|file| fmt_file(file, true)
+	} else {
+		|file| fmt_file(file, false)
+	};
+	each_file(files, fmt_one)
+}
+
+fn fmt_file(file: Option<&str>, check: bool) -> CliResult {
+	let text = read_all(file)?;
+	let mut index = LineIndex::new(());
+	index.scan(&text);
+	let document = Reader::new(&*text)
+		.collect::<Result<Document, _>>()
+		.map_err(|err| report(file, &index, err))?;
+	let mut formatted = document.to_string();
+	if !formatted.is_empty() {
+		formatted.push('\n');
+	}
+	if formatted.as_bytes() == text {
+		return Ok(());
+	}
+	let name = file_name(file);
+	if check {
+		eprintln!("{name}: would reformat");
+		Err(Failure::Reported)
+	} else if name == "-" {
+		stdout()
+			.write_all(formatted.as_bytes())
+			.map_err(output_error)
+	} else {
+		write(name, formatted).map_err(|err| Failure::Io(name.into(), err))
+	}
+}
+
+fn check(file: Option<&str>) -> CliResult { stream(file, |_| Ok(())) }
+
+fn events(file: Option<&str>) -> CliResult {
+	let mut output = BufWriter::new(stdout().lock());
+	stream(file, |(event, span)| {
+		writeln!(output, "{event:?} {span:?}").map_err(output_error)
+	})?;
+	output.flush().map_err(output_error)
+}
+
+fn convert(to: &str, file: Option<&str>) -> CliResult {
+	let mut output = BufWriter::new(stdout().lock());
+	match to {
+		"json" => {
+			let mut writer = json::JsonWriter::new(&mut output).map_err(output_error)?;
+			stream(file, |(event, _)| writer.push(&event).map_err(output_error))?;
+			writer.finish().map_err(output_error)?;
+			writeln!(output).map_err(output_error)?;
+		}
+		"kdl" => {
+			let text = read_all(file)?;
+			let document = json::parse_document(&text).map_err(|(message, offset)| {
+				eprintln!("{}: byte {offset}: {message}", file_name(file));
+				Failure::Reported
+			})?;
+			if !document.nodes.is_empty() {
+				writeln!(output, "{document}").map_err(output_error)?;
+			}
+		}
+		_ => return Err(Failure::Usage(format!("unknown format {to:?}"))),
+	}
+	output.flush().map_err(output_error)
+}
+
+fn lsp() -> CliResult {
+	match lsp::run(stdin().lock(), stdout().lock()) {
+		Ok(true) => Ok(()),
+		// exit without shutdown
+		Ok(false) => Err(Failure::Reported),
+		Err(err) => Err(Failure::Io("<stdio>".into(), err)),
+	}
+}
+
+fn query(path: &str, file: Option<&str>) -> CliResult {
+	let path = path.split('/').collect::<Vec<_>>();
+	let mut output = BufWriter::new(stdout().lock());
+	// whether each open node matches its path segment
+	let mut matched = Vec::<bool>::new();
+	let mut builder = None::<DocumentBuilder>;
+	stream(file, |(event, _)| {
+		let depth = matched.len();
+		if let Event::Node { name, .. } = &event {
+			let parent = matched.last().copied().unwrap_or(true);
+			let segment = path.get(depth);
+			let hit = parent && segment.is_some_and(|&segment| segment == "*" || segment == name);
+			matched.push(hit);
+			if hit && depth + 1 == path.len() && builder.is_none() {
+				builder = Some(DocumentBuilder::new());
+			}
+		} else if event == Event::End {
+			matched.pop();
+		}
+		if let Some(inner) = &mut builder
+			&& let Some(node) = inner.push(event)
+		{
+			builder = None;
+			writeln!(output, "{node}").map_err(output_error)?;
+		}
+		Ok(())
+	})?;
+	output.flush().map_err(output_error)
+}
@@ -0,0 +1,386 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Compact binary encoding of event streams.
+//!
+//! Decoding is much faster than reading text, and loses nothing but
+//! formatting, which makes it useful for caches and sending documents between
+//! processes.
+//!
+//! # Format
+//! - Header: `KDLB` then a version byte, currently `1`.
+//! - Events, each starting with a tag byte:
+//!   - `0x00`: end of the stream.
+//!   - `0x01 name`: [`Event::Node`] without a type.
+//!   - `0x02 type name`: [`Event::Node`] with a type.
+//!   - `0x03`: [`Event::Children`].
+//!   - `0x04`: [`Event::End`].
+//!   - `0x20 | kind << 2 | has_type << 1 | has_name`, then `name` and `type` if
+//!     present, then the value: [`Event::Entry`]. `kind` is `0` for a string
+//!     value, `1` for a number, `2` for `#false`, `3` for `#true`, and `4` for
+//!     `#null`.
+//! - Varints are unsigned LEB128.
+//! - Strings (and numbers, in the internal form of [`Number`]) are a varint
+//!   `n`, where `0` is followed by a new string, as a varint byte length and
+//!   UTF-8 bytes, and anything else is the `n - 1`th new string so far.
+//!
+//! You probably want to start at [`Encoder`] and [`Decoder`].
+//!
+//! [`Number`]: crate::dom::Number
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as Map;
+use alloc::vec::Vec;
+use core::error::Error;
+#[cfg(feature = "std")]
+use std::collections::HashMap as Map;
+
+use displaydoc::Display;
+use smol_str::SmolStr;
+
+use crate::dom::{Entry, Event, EventRef, Number, Value};
+use crate::validator::{Validator, ValidatorError};
+
+const MAGIC: &[u8; 4] = b"KDLB";
+/// Current format version.
+pub const VERSION: u8 = 1;
+
+const TAG_DONE: u8 = 0x00;
+const TAG_NODE: u8 = 0x01;
+const TAG_TYPED_NODE: u8 = 0x02;
+const TAG_CHILDREN: u8 = 0x03;
+const TAG_END: u8 = 0x04;
+const TAG_ENTRY: u8 = 0x20;
+const ENTRY_NAME: u8 = 0b01;
+const ENTRY_TYPE: u8 = 0b10;
+const KIND_STRING: u8 = 0;
+const KIND_NUMBER: u8 = 1;
+const KIND_FALSE: u8 = 2;
+const KIND_TRUE: u8 = 3;
+const KIND_NULL: u8 = 4;
+
+/// An error while decoding.
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum BinaryError {
+	/// Not a binary event stream
+	BadHeader,
+	/// Unsupported version {0}
+	Version(u8),
+	/// Unexpected end of input at {0}
+	UnexpectedEof(usize),
+	/// Invalid tag {0:#04x} at {1}
+	BadTag(u8, usize),
+	/// Invalid varint at {0}
+	BadVarint(usize),
+	/// Invalid string at {0}
+	BadString(usize),
+	/// Invalid number at {0}
+	BadNumber(usize),
+	/// Invalid event stream: {0}
+	Invalid(ValidatorError),
+}
+impl Error for BinaryError {}
+
+/// Encoder of events to the binary format.
+///
+/// Like the [`Writer`](crate::writer::Writer), this trusts its input, an
+/// invalid event stream produces output that fails to decode.
+///
+/// ```
+/// # use just_kdl::binary::{Decoder, Encoder};
+/// # use just_kdl::dom::{Document, Event};
+/// # use just_kdl::reader::Reader;
+/// let text = "node 1 key=#true { child \"text\" }";
+/// let mut encoder = Encoder::new();
+/// for result in Reader::new(text.as_bytes()) {
+///     encoder.push(&result.unwrap().0);
+/// }
+/// let bytes = encoder.finish();
+/// let document = Decoder::new(&bytes)?.collect::<Result<Document, _>>()?;
+/// assert_eq!(document.to_string(), "node 1 key=#true {\n    child text\n}");
+/// # Ok::<(), just_kdl::binary::BinaryError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Encoder {
+	output: Vec<u8>,
+	/// interned string → index
+	table: Map<SmolStr, usize>,
+}
+
+impl Default for Encoder {
+	fn default() -> Self { Self::new() }
+}
+
+impl Encoder {
+	/// Create a new encoder, and write the header.
+	pub fn new() -> Self {
+		let mut output = Vec::from(*MAGIC);
+		output.push(VERSION);
+		Self {
+			output,
+			table: Map::new(),
+		}
+	}
+	/// Encode an event.
+	pub fn push<'event, E: Into<EventRef<'event>>>(&mut self, event: E) {
+		match event.into() {
+			EventRef::Node { r#type: None, name } => {
+				self.output.push(TAG_NODE);
+				self.string(name);
+			}
+			EventRef::Node {
+				r#type: Some(r#type),
+				name,
+			} => {
+				self.output.push(TAG_TYPED_NODE);
+				self.string(r#type);
+				self.string(name);
+			}
+			EventRef::Entry(Entry {
+				name,
+				r#type,
+				value,
+			}) => {
+				let kind = match value {
+					Value::String(_) => KIND_STRING,
+					Value::Number(_) => KIND_NUMBER,
+					Value::Bool(false) => KIND_FALSE,
+					Value::Bool(true) => KIND_TRUE,
+					Value::Null => KIND_NULL,
+				};
+				let mut tag = TAG_ENTRY | kind << 2;
+				if name.is_some() {
+					tag |= ENTRY_NAME;
+				}
+				if r#type.is_some() {
+					tag |= ENTRY_TYPE;
+				}
+				self.output.push(tag);
+				if let Some(name) = name {
+					self.string(name);
+				}
+				if let Some(r#type) = r#type {
+					self.string(r#type);
+				}
+				match value {
+					Value::String(text) => self.string(text),
+					Value::Number(number) => self.string(&number.0),
+					Value::Bool(_) | Value::Null => {}
+				}
+			}
+			EventRef::Children => self.output.push(TAG_CHILDREN),
+			EventRef::End => self.output.push(TAG_END),
+		}
+	}
+	/// Mark the end of the event stream, and return the output.
+	pub fn finish(mut self) -> Vec<u8> {
+		self.output.push(TAG_DONE);
+		self.output
+	}
+	fn varint(&mut self, mut value: usize) {
+		while value >= 0x80 {
+			#[expect(clippy::cast_possible_truncation, reason = "low bits")]
+			self.output.push(value as u8 | 0x80);
+			value >>= 7;
+		}
+		#[expect(clippy::cast_possible_truncation, reason = "checked")]
+		self.output.push(value as u8);
+	}
+	fn string(&mut self, text: &SmolStr) {
+		if let Some(&index) = self.table.get(text) {
+			self.varint(index + 1);
+		} else {
+			self.table.insert(text.clone(), self.table.len());
+			self.output.push(0);
+			self.varint(text.len());
+			self.output.extend_from_slice(text.as_bytes());
+		}
+	}
+}
+
+/// Decoder of events from the binary format.
+///
+/// This produces the same events the [`Reader`](crate::reader::Reader) read
+/// before encoding, and rejects invalid event streams. See [`Encoder`] for an
+/// example.
+#[derive(Debug)]
+pub struct Decoder<'data> {
+	data: &'data [u8],
+	cursor: usize,
+	table: Vec<SmolStr>,
+	/// `None` once done or failed
+	validator: Option<Validator>,
+}
+
+impl<'data> Decoder<'data> {
+	/// Create a new decoder, and check the header.
+	///
+	/// # Errors
+	/// If the header is invalid or for an unsupported version.
+	pub fn new(data: &'data [u8]) -> Result<Self, BinaryError> {
+		let Some((magic, rest)) = data.split_first_chunk::<4>() else {
+			return Err(BinaryError::BadHeader);
+		};
+		if magic != MAGIC {
+			return Err(BinaryError::BadHeader);
+		}
+		match rest.first() {
+			None => Err(BinaryError::BadHeader),
+			Some(&VERSION) => Ok(Self {
+				data,
+				cursor: MAGIC.len() + 1,
+				table: Vec::new(),
+				validator: Some(Validator::new()),
+			}),
+			Some(&version) => Err(BinaryError::Version(version)),
+		}
+	}
+	fn byte(&mut self) -> Result<u8, BinaryError> {
+		let byte = *self
+			.data
+			.get(self.cursor)
+			.ok_or(BinaryError::UnexpectedEof(self.cursor))?;
+		self.cursor += 1;
+		Ok(byte)
+	}
+	fn varint(&mut self) -> Result<usize, BinaryError> {
+		let start = self.cursor;
+		let mut value = 0_usize;
+		let mut shift = 0;
+		loop {
+			let byte = self.byte()?;
+			let low = usize::from(byte & 0x7F);
+			if shift >= usize::BITS || (low << shift) >> shift != low {
+				return Err(BinaryError::BadVarint(start));
+			}
+			value |= low << shift;
+			if byte & 0x80 == 0 {
+				return Ok(value);
+			}
+			shift += 7;
+		}
+	}
+	fn string(&mut self) -> Result<SmolStr, BinaryError> {
+		let start = self.cursor;
+		let index = self.varint()?;
+		if let Some(index) = index.checked_sub(1) {
+			return self
+				.table
+				.get(index)
+				.cloned()
+				.ok_or(BinaryError::BadString(start));
+		}
+		let len = self.varint()?;
+		let bytes = self
+			.cursor
+			.checked_add(len)
+			.and_then(|end| self.data.get(self.cursor..end))
+			.ok_or(BinaryError::UnexpectedEof(self.data.len()))?;
+		let text = SmolStr::new(str::from_utf8(bytes).map_err(|_| BinaryError::BadString(start))?);
+		self.cursor += len;
+		self.table.push(text.clone());
+		Ok(text)
+	}
+	fn event(&mut self) -> Result<Option<Event>, BinaryError> {
+		let start = self.cursor;
+		let tag = self.byte()?;
+		Ok(Some(match tag {
+			TAG_DONE => return Ok(None),
+			TAG_NODE => Event::Node {
+				r#type: None,
+				name: self.string()?,
+			},
+			TAG_TYPED_NODE => Event::Node {
+				r#type: Some(self.string()?),
+				name: self.string()?,
+			},
+			TAG_CHILDREN => Event::Children,
+			TAG_END => Event::End,
+			_ if tag & !0b1_1111 == TAG_ENTRY => {
+				let name = (tag & ENTRY_NAME != 0).then(|| self.string()).transpose()?;
+				let r#type = (tag & ENTRY_TYPE != 0).then(|| self.string()).transpose()?;
+				let value = match (tag & 0b1_1111) >> 2 {
+					KIND_STRING => Value::String(self.string()?),
+					KIND_NUMBER => {
+						let number_start = self.cursor;
+						let text = self.string()?;
+						if !valid_number(&text) {
+							return Err(BinaryError::BadNumber(number_start));
+						}
+						Value::Number(Number(text))
+					}
+					KIND_FALSE => Value::Bool(false),
+					KIND_TRUE => Value::Bool(true),
+					KIND_NULL => Value::Null,
+					_ => return Err(BinaryError::BadTag(tag, start)),
+				};
+				Event::Entry(Entry {
+					name,
+					r#type,
+					value,
+				})
+			}
+			_ => return Err(BinaryError::BadTag(tag, start)),
+		}))
+	}
+}
+
+impl Iterator for Decoder<'_> {
+	type Item = Result<Event, BinaryError>;
+	fn next(&mut self) -> Option<Self::Item> {
+		self.validator.as_ref()?;
+		let result = match self.event() {
+			Ok(Some(event)) => match self.validator.as_mut()?.push(&event) {
+				Ok(()) => return Some(Ok(event)),
+				Err(err) => Err(BinaryError::Invalid(err)),
+			},
+			Ok(None) => match self.validator.take()?.done() {
+				Ok(()) => return None,
+				Err(err) => Err(BinaryError::Invalid(err)),
+			},
+			Err(err) => Err(err),
+		};
+		self.validator = None;
+		Some(result)
+	}
+}
+
+/// check the internal form of a [`Number`]
+fn valid_number(text: &str) -> bool {
+	/// one or more digits
+	fn digits(text: &str, digit: fn(&u8) -> bool) -> bool {
+		!text.is_empty() && text.as_bytes().iter().all(digit)
+	}
+	/// `-?` then digits
+	fn signed(text: &str, digit: fn(&u8) -> bool) -> bool {
+		digits(text.strip_prefix('-').unwrap_or(text), digit)
+	}
+	// the first byte must be ASCII
+	let Some(body) = text.get(1..) else {
+		return false;
+	};
+	match text.as_bytes()[0] {
+		b'#' => matches!(text, "#inf" | "#-inf" | "#nan"),
+		b'x' => signed(body, u8::is_ascii_hexdigit),
+		b'd' => signed(body, u8::is_ascii_digit),
+		b'o' => signed(body, |byte| matches!(byte, b'0'..=b'7')),
+		b'b' => signed(body, |byte| matches!(byte, b'0' | b'1')),
+		b'f' => {
+			// floats converted from primitives use a lowercase `e`
+			let (mantissa, exponent) = match body.split_once(['E', 'e']) {
+				Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+				None => (body, None),
+			};
+			let (int, fraction) = match mantissa.split_once('.') {
+				Some((int, fraction)) => (int, Some(fraction)),
+				None => (mantissa, None),
+			};
+			signed(int, u8::is_ascii_digit)
+				&& fraction.is_none_or(|fraction| digits(fraction, u8::is_ascii_digit))
+				&& exponent.is_none_or(|exponent| {
+					let exponent = exponent.strip_prefix('+').unwrap_or(exponent);
+					signed(exponent, u8::is_ascii_digit)
+				}) && (fraction.is_some() || exponent.is_some())
+		}
+		_ => false,
+	}
+}
@@ -11,6 +11,7 @@ use alloc::string::String;
 use alloc::vec::Vec;
 use core::convert::Infallible;
 use core::fmt;
+use core::hash::Hasher;
 use core::ops::{Index, IndexMut};
 use core::ptr::eq as ptr_eq;
 
@@ -18,9 +19,19 @@ use smol_str::SmolStr;
 
 use crate::IdentDisplay;
 use crate::writer::Writer;
+#[cfg(feature = "std")]
+use crate::writer::{IoWriter, WriterError};
 
+pub mod canonical;
+pub mod include;
+#[cfg(feature = "kdl-interop")]
+#[cfg_attr(docsrs, doc(cfg(feature = "kdl-interop")))]
+pub mod interop;
+pub mod interpolate;
 pub mod iter;
 pub mod number;
+pub mod spanned;
+pub mod visit;
 
 /// debug an `Option<T>` as just `T` or `None`
 fn option_debug<T: fmt::Debug>(value: Option<&T>) -> &dyn fmt::Debug {
@@ -52,14 +63,66 @@ impl Document {
 	}
 	/// Iterate over the [`Event`]s of this document.
 	pub fn iter(&self) -> iter::Iter<'_> { self.into_iter() }
-	/// Normalize document to kdl spec by [`normalize`]-ing child nodes.
+	/// Iterate over the borrowed [`EventRef`]s of this document, without
+	/// cloning.
+	pub fn iter_ref(&self) -> iter::RefIter<'_> { iter::RefIter::from_document(self) }
+	/// Write this document to an IO stream, like [`Display`] but keeping IO
+	/// errors.
 	///
-	/// [`normalize`]: Node::normalize
+	/// [`Display`]: fmt::Display
+	///
+	/// # Errors
+	/// If the output errors.
 	#[cfg(feature = "std")]
-	pub fn normalize(&mut self) {
-		for node in &mut self.nodes {
-			node.normalize();
+	#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+	#[expect(clippy::absolute_paths, reason = "feature-gated")]
+	pub fn write_to<W: std::io::Write>(&self, output: W) -> Result<(), WriterError> {
+		let mut writer = IoWriter::new(output);
+		for event in self.iter_ref() {
+			writer.push(event)?;
 		}
+		writer.finish().map(drop)
+	}
+	/// Normalize document to kdl spec by [`normalize`]-ing child nodes.
+	///
+	/// [`normalize`]: Node::normalize
+	pub fn normalize(&mut self) { self.visit_mut(&mut visit::Normalize); }
+	/// Traverse this document with a [`Visitor`](visit::Visitor), see the
+	/// [`visit`] module for the order of hooks.
+	pub fn visit<V: visit::Visitor + ?Sized>(&self, visitor: &mut V) {
+		visit::nodes(visitor, &self.nodes, &mut visit::Path::default());
+	}
+	/// Traverse and edit this document with a
+	/// [`VisitorMut`](visit::VisitorMut), see the [`visit`] module for the
+	/// order of hooks.
+	pub fn visit_mut<V: visit::VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+		visit::nodes_mut(visitor, &mut self.nodes, &mut visit::Path::default());
+	}
+	/// The canonical form of this document, the same for documents with the
+	/// same meaning, see the [`canonical`] module for details.
+	///
+	/// ```
+	/// # use just_kdl::dom::Document;
+	/// # use just_kdl::reader::Reader;
+	/// let read = |text: &str| Reader::new(text.as_bytes()).collect::<Result<Document, _>>();
+	/// let a = read("node b=2 a=1 0x10 a=3 {}")?;
+	/// let b = read("node a=3 16.0 b=2")?;
+	/// assert_eq!(a.canonical().to_string(), "\"node\" 16 \"a\"=3 \"b\"=2\n");
+	/// assert_eq!(a.canonical().to_string(), b.canonical().to_string());
+	/// # Ok::<(), just_kdl::reader::Spanned<just_kdl::reader::ReaderError>>(())
+	/// ```
+	pub fn canonical(&self) -> canonical::Canonical<'_> { canonical::Canonical(&self.nodes) }
+	/// Feed the bytes of the [`canonical`] form of this document to `hasher`
+	/// as they're produced, without building the text first.
+	///
+	/// Unlike the [`Hash`] implementation, this is the same for documents with
+	/// the same meaning, and stable across crate and compiler versions (for a
+	/// stable `hasher`). Wrap a cryptographic digest in a [`Hasher`] to sign
+	/// documents.
+	pub fn canonical_hash<H: Hasher>(&self, hasher: &mut H) {
+		// hashers never fail
+		canonical::write_nodes(&mut canonical::HashOutput(hasher), &self.nodes, 0)
+			.unwrap_or_else(|_| unreachable!());
 	}
 }
 
@@ -72,8 +135,8 @@ impl fmt::Debug for Document {
 impl fmt::Display for Document {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let mut writer = Writer::new(f);
-		for event in self {
-			writer.push(&event)?;
+		for event in self.iter_ref() {
+			writer.push(event)?;
 		}
 		Ok(())
 	}
@@ -145,26 +208,54 @@ impl Node {
 	}
 	/// Iterate over the [`Event`]s of this node.
 	pub fn iter(&self) -> iter::Iter<'_> { self.into_iter() }
+	/// Iterate over the borrowed [`EventRef`]s of this node, without cloning.
+	pub fn iter_ref(&self) -> iter::RefIter<'_> { iter::RefIter::from_node(self) }
+	/// Write this node to an IO stream, like [`Display`] but keeping IO
+	/// errors.
+	///
+	/// [`Display`]: fmt::Display
+	///
+	/// # Errors
+	/// If the output errors.
+	#[cfg(feature = "std")]
+	#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+	#[expect(clippy::absolute_paths, reason = "feature-gated")]
+	pub fn write_to<W: std::io::Write>(&self, output: W) -> Result<(), WriterError> {
+		let mut writer = IoWriter::new(output);
+		for event in self.iter_ref() {
+			writer.push(event)?;
+		}
+		writer.finish().map(drop)
+	}
 	/// Normalize node to kdl spec:
 	/// - Empty children block gets removed
 	/// - Normalize child document
 	/// - Duplicate properties are removed
-	#[cfg(feature = "std")]
 	pub fn normalize(&mut self) {
-		use std::collections::HashSet;
 		if let Some(children) = &mut self.children {
-			if children.nodes.is_empty() {
-				self.children = None;
-			} else {
-				children.normalize();
-			}
+			children.normalize();
+		}
+		self.normalize_shallow();
+	}
+	/// [`normalize`](Self::normalize) without the child document.
+	fn normalize_shallow(&mut self) {
+		#[cfg(not(feature = "std"))]
+		use alloc::collections::BTreeSet as Set;
+		#[cfg(feature = "std")]
+		use std::collections::HashSet as Set;
+		if self
+			.children
+			.as_ref()
+			.is_some_and(|children| children.nodes.is_empty())
+		{
+			self.children = None;
 		}
 		// TODO: this is simply an unlikely string-pointer
 		// consider a real way to get a fake/random string pointer
 		// or otherwise mark indexes as used with few allocatioons
 		let marker = SmolStr::new_static(&"\0temp"[5..]);
 		// replace duplicate props with marker (in reverse order)
-		let mut seen = HashSet::new();
+		let mut seen = Set::new();
 		for entry in self.entries.iter_mut().rev() {
 			if let Some(name) = &mut entry.name {
 				if seen.contains(&**name) {
@@ -197,8 +288,8 @@ impl fmt::Debug for Node {
 impl fmt::Display for Node {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let mut writer = Writer::new(f);
-		for event in self {
-			writer.push(&event)?;
+		for event in self.iter_ref() {
+			writer.push(event)?;
 		}
 		Ok(())
 	}
@@ -422,7 +513,7 @@ impl<T: Into<Value>> From<Option<T>> for Value {
 /// can convert to/from standard number types as needed.
 // implementations in `number` module
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Number(SmolStr);
+pub struct Number(pub(crate) SmolStr);
 
 /// A document-stream event.
 #[derive(Clone, PartialEq, Eq, Hash)]
@@ -456,3 +547,67 @@ impl fmt::Debug for Event {
 		}
 	}
 }
+
+/// A borrowed document-stream event, like [`Event`] but without cloning the
+/// document's strings and entries.
+///
+/// [`Writer`], [`Validator`](crate::validator::Validator), and
+/// [`DocumentBuilder`](iter::DocumentBuilder) accept both.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventRef<'doc> {
+	/// Beginning of a node, terminated by a matching `End` event.
+	Node {
+		/// Optional node type hint.
+		r#type: Option<&'doc SmolStr>,
+		/// Node name.
+		name: &'doc SmolStr,
+	},
+	/// A property or value on the `Node`.
+	Entry(&'doc Entry),
+	/// The beginning of the `Node`s children.
+	Children,
+	/// The end of the `Node` and its children block.
+	End,
+}
+
+impl fmt::Debug for EventRef<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Node { r#type, name } => f
+				.debug_struct("Node")
+				.field("type", option_debug(r#type.as_ref()))
+				.field("name", name)
+				.finish(),
+			Self::Entry(entry) => fmt::Debug::fmt(entry, f),
+			Self::Children => f.write_str("Children"),
+			Self::End => f.write_str("End"),
+		}
+	}
+}
+
+impl<'doc> From<&'doc Event> for EventRef<'doc> {
+	fn from(event: &'doc Event) -> Self {
+		match event {
+			Event::Node { r#type, name } => Self::Node {
+				r#type: r#type.as_ref(),
+				name,
+			},
+			Event::Entry(entry) => Self::Entry(entry),
+			Event::Children => Self::Children,
+			Event::End => Self::End,
+		}
+	}
+}
+impl From<EventRef<'_>> for Event {
+	fn from(event: EventRef) -> Self {
+		match event {
+			EventRef::Node { r#type, name } => Self::Node {
+				r#type: r#type.cloned(),
+				name: name.clone(),
+			},
+			EventRef::Entry(entry) => Self::Entry(entry.clone()),
+			EventRef::Children => Self::Children,
+			EventRef::End => Self::End,
+		}
+	}
+}
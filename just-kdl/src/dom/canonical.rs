@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Canonical text form of documents, for hashing and signing.
+//!
+//! Documents with the same meaning have the same canonical form, byte for
+//! byte. The form is part of the crate's stability guarantee and only
+//! changes with a major version:
+//! - Every node is on its own line, ending in `\n`, children are indented by
+//!   four spaces per level.
+//! - Empty children blocks are removed, see [`Node::normalize`].
+//! - Arguments keep their order, followed by properties sorted by name (by
+//!   UTF-8 bytes), with duplicates removed (right-most wins).
+//! - Entries are separated by a single space, with no spaces around `=` or type
+//!   annotations.
+//! - Names, types, and strings are always quoted, escaping `"`, `\`, and `\n \r
+//!   \t \b \f` by name, other newlines and disallowed code points as `\u{HEX}`
+//!   with uppercase digits, and nothing else.
+//! - Numbers are written by their exact decimal value, so `0x10`, `16.0`, and
+//!   `1.6e1` are all `16`, laid out like ECMAScript's `Number::toString`: plain
+//!   digits up to 21 digits before the point or 6 zeros after it, and
+//!   `D.DDDE+N` otherwise. `#inf`, `#-inf`, and `#nan` are unchanged.
+//!
+//! You probably want to start at [`Document::canonical`].
+//!
+//! [`Document::canonical`]: super::Document::canonical
+//! [`Node::normalize`]: super::Node::normalize
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::hash::Hasher;
+use core::iter::from_fn;
+
+use super::{Entry, Node, Number, Value};
+use crate::dom::number::Description;
+
+/// [`Display`](fmt::Display) of the canonical form of a [`Document`], see
+/// [`Document::canonical`].
+///
+/// [`Document`]: super::Document
+/// [`Document::canonical`]: super::Document::canonical
+#[derive(Debug, Clone, Copy)]
+pub struct Canonical<'doc>(pub(super) &'doc [Node]);
+
+impl fmt::Display for Canonical<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write_nodes(f, self.0, 0) }
+}
+
+/// Feeds text to a [`Hasher`].
+pub(super) struct HashOutput<'hasher, H>(pub(super) &'hasher mut H);
+
+impl<H: Hasher> fmt::Write for HashOutput<'_, H> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.0.write(s.as_bytes());
+		Ok(())
+	}
+}
+
+pub(super) fn write_nodes<W: fmt::Write>(out: &mut W, nodes: &[Node], depth: usize) -> fmt::Result {
+	for node in nodes {
+		for _ in 0..depth {
+			out.write_str("    ")?;
+		}
+		if let Some(r#type) = &node.r#type {
+			write_type(out, r#type)?;
+		}
+		write_string(out, &node.name)?;
+		let mut properties = node
+			.entries
+			.iter()
+			.filter(|entry| entry.name.is_some())
+			.collect::<Vec<_>>();
+		// stable, so the right-most duplicate is last
+		properties.sort_by(|a, b| a.name.cmp(&b.name));
+		let arguments = node.entries.iter().filter(|entry| entry.name.is_none());
+		let mut properties = properties.iter().peekable();
+		let properties = from_fn(|| {
+			loop {
+				let entry = properties.next()?;
+				if properties.peek().is_none_or(|next| next.name != entry.name) {
+					return Some(*entry);
+				}
+			}
+		});
+		for entry in arguments.chain(properties) {
+			out.write_char(' ')?;
+			write_entry(out, entry)?;
+		}
+		match &node.children {
+			Some(children) if !children.nodes.is_empty() => {
+				out.write_str(" {\n")?;
+				write_nodes(out, &children.nodes, depth + 1)?;
+				for _ in 0..depth {
+					out.write_str("    ")?;
+				}
+				out.write_str("}\n")?;
+			}
+			_ => out.write_char('\n')?,
+		}
+	}
+	Ok(())
+}
+
+fn write_entry<W: fmt::Write>(out: &mut W, entry: &Entry) -> fmt::Result {
+	if let Some(name) = &entry.name {
+		write_string(out, name)?;
+		out.write_char('=')?;
+	}
+	if let Some(r#type) = &entry.r#type {
+		write_type(out, r#type)?;
+	}
+	match &entry.value {
+		Value::String(text) => write_string(out, text),
+		Value::Number(number) => write_number(out, number),
+		Value::Bool(true) => out.write_str("#true"),
+		Value::Bool(false) => out.write_str("#false"),
+		Value::Null => out.write_str("#null"),
+	}
+}
+
+fn write_type<W: fmt::Write>(out: &mut W, r#type: &str) -> fmt::Result {
+	out.write_char('(')?;
+	write_string(out, r#type)?;
+	out.write_char(')')
+}
+
+fn write_string<W: fmt::Write>(out: &mut W, text: &str) -> fmt::Result {
+	out.write_char('"')?;
+	for ch in text.chars() {
+		match ch {
+			'"' => out.write_str("\\\""),
+			'\\' => out.write_str("\\\\"),
+			'\n' => out.write_str("\\n"),
+			'\r' => out.write_str("\\r"),
+			'\t' => out.write_str("\\t"),
+			'\u{8}' => out.write_str("\\b"),
+			'\u{C}' => out.write_str("\\f"),
+			'\u{0}'..='\u{1F}'
+			| '\u{7F}'
+			| '\u{85}'
+			| '\u{200E}'
+			| '\u{200F}'
+			| '\u{2028}'
+			| '\u{2029}'
+			| '\u{202A}'..='\u{202E}'
+			| '\u{2066}'..='\u{2069}'
+			| '\u{FEFF}' => write!(out, "\\u{{{:X}}}", u32::from(ch)),
+			_ => out.write_char(ch),
+		}?;
+	}
+	out.write_char('"')
+}
+
+fn write_number<W: fmt::Write>(out: &mut W, number: &Number) -> fmt::Result {
+	let (negative, digits, exponent) = match number.describe() {
+		Description::Special { text } => return out.write_str(text),
+		Description::Integer { base, text } => {
+			let (negative, text) = sign(text);
+			(negative, to_decimal(text, base.as_radix()), 0)
+		}
+		Description::Float { text } => {
+			let (negative, text) = sign(text);
+			let (mantissa, exp_text) = text.split_once(['E', 'e']).unwrap_or((text, "0"));
+			let (int, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+			let (exp_negative, exp_digits) = sign(exp_text.trim_start_matches('+'));
+			// saturating, exponents that large are never meaningful anyways
+			let magnitude = exp_digits.bytes().fold(0_i128, |acc, digit| {
+				acc.saturating_mul(10)
+					.saturating_add(i128::from(digit - b'0'))
+			});
+			let exponent = if exp_negative { -magnitude } else { magnitude };
+			let mut digits = String::from(int);
+			digits.push_str(fraction);
+			(
+				negative,
+				digits,
+				exponent.saturating_sub(fraction.len() as i128),
+			)
+		}
+	};
+	let digits = digits.trim_start_matches('0');
+	let significant = digits.trim_end_matches('0');
+	if significant.is_empty() {
+		return out.write_char('0');
+	}
+	if negative {
+		out.write_char('-')?;
+	}
+	let count = significant.len() as i128;
+	// value is 0.{significant} × 10^point
+	let point = exponent.saturating_add(digits.len() as i128);
+	if count <= point && point <= 21 {
+		out.write_str(significant)?;
+		zeros(out, point - count)
+	} else if 0 < point && point <= 21 {
+		let (int, fraction) = significant.split_at(point.unsigned_abs() as usize);
+		write!(out, "{int}.{fraction}")
+	} else if -6 < point && point <= 0 {
+		out.write_str("0.")?;
+		zeros(out, -point)?;
+		out.write_str(significant)
+	} else {
+		let (first, rest) = significant.split_at(1);
+		out.write_str(first)?;
+		if !rest.is_empty() {
+			write!(out, ".{rest}")?;
+		}
+		let shown = point - 1;
+		let sign = if shown < 0 { '-' } else { '+' };
+		write!(out, "E{sign}{}", shown.unsigned_abs())
+	}
+}
+
+fn zeros<W: fmt::Write>(out: &mut W, count: i128) -> fmt::Result {
+	(0..count).try_for_each(|_| out.write_char('0'))
+}
+
+fn sign(text: &str) -> (bool, &str) {
+	match text.strip_prefix('-') {
+		Some(rest) => (true, rest),
+		None => (false, text),
+	}
+}
+
+/// Convert digits in `radix` to decimal digits.
+fn to_decimal(text: &str, radix: u32) -> String {
+	/// little-endian base-10⁹ limbs
+	const LIMB: u64 = 1_000_000_000;
+	if radix == 10 {
+		return text.into();
+	}
+	let mut limbs = Vec::<u64>::new();
+	for digit in text.chars() {
+		let mut carry = u64::from(digit.to_digit(radix).unwrap_or(0));
+		for limb in &mut limbs {
+			let value = *limb * u64::from(radix) + carry;
+			*limb = value % LIMB;
+			carry = value / LIMB;
+		}
+		if carry > 0 {
+			limbs.push(carry);
+		}
+	}
+	let mut out = String::new();
+	let mut limbs = limbs.iter().rev();
+	// writing to a string never fails
+	if let Some(first) = limbs.next() {
+		write!(out, "{first}").unwrap_or_else(|_| unreachable!());
+	}
+	for limb in limbs {
+		write!(out, "{limb:09}").unwrap_or_else(|_| unreachable!());
+	}
+	out
+}
@@ -0,0 +1,338 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Resolving `include "path.kdl"` nodes across files.
+//!
+//! Each include node is replaced by the nodes of the file it names, at any
+//! depth, with files found through a [`Resolver`]. Every node remembers
+//! which file it came from, see [`IncludedDocument::file`].
+//!
+//! You probably want to start at [`Includes`].
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::error::Error;
+use core::fmt;
+use core::mem::take;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::fs::read;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::Path;
+
+use displaydoc::Display;
+use smol_str::SmolStr;
+
+use super::spanned::{DocumentSpans, NodeSpans, SpannedDocument};
+use super::{Document, Entry, Node, Value};
+use crate::reader::{Reader, ReaderError};
+
+/// A file found by a [`Resolver`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Source {
+	/// Unique name of the file, used to detect cycles and in spans.
+	pub name: String,
+	/// Contents of the file.
+	pub text: Vec<u8>,
+}
+
+/// Finds included files, e.g. on the filesystem or in embedded assets.
+pub trait Resolver {
+	/// An error while finding a file.
+	type Error;
+	/// Find the file at `path`, included from the file named `from`, or the
+	/// root file if `None`.
+	///
+	/// # Errors
+	/// If the file can't be found or read.
+	fn resolve(&mut self, from: Option<&str>, path: &str) -> Result<Source, Self::Error>;
+}
+
+/// Error of in-memory [`Resolver`]s.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+/// File not found
+pub struct NotFound;
+impl Error for NotFound {}
+
+/// Files by their exact path, e.g. embedded assets.
+impl<K: Borrow<str> + Ord, V: AsRef<[u8]>> Resolver for BTreeMap<K, V> {
+	type Error = NotFound;
+	fn resolve(&mut self, _: Option<&str>, path: &str) -> Result<Source, Self::Error> {
+		let text = self.get(path).ok_or(NotFound)?;
+		Ok(Source {
+			name: path.into(),
+			text: text.as_ref().into(),
+		})
+	}
+}
+
+/// Files by their exact path, e.g. embedded assets.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K: Borrow<str> + Eq + Hash, V: AsRef<[u8]>, S: BuildHasher> Resolver for HashMap<K, V, S> {
+	type Error = NotFound;
+	fn resolve(&mut self, _: Option<&str>, path: &str) -> Result<Source, Self::Error> {
+		let text = self.get(path).ok_or(NotFound)?;
+		Ok(Source {
+			name: path.into(),
+			text: text.as_ref().into(),
+		})
+	}
+}
+
+/// Files on the filesystem, with paths relative to the including file.
+///
+/// Files are named by their canonical path, so the same file is detected
+/// through any path to it.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FsResolver;
+
+#[cfg(feature = "std")]
+impl Resolver for FsResolver {
+	type Error = io::Error;
+	fn resolve(&mut self, from: Option<&str>, path: &str) -> Result<Source, Self::Error> {
+		let base = from
+			.and_then(|from| Path::new(from).parent())
+			.unwrap_or(Path::new(""));
+		let full = base.join(path).canonicalize()?;
+		let text = read(&full)?;
+		Ok(Source {
+			name: full.to_string_lossy().into_owned(),
+			text,
+		})
+	}
+}
+
+/// An error while resolving includes.
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum IncludeError<E> {
+	/// Couldn't resolve {0:?}: {1}
+	Resolve(String, E),
+	/// {0}
+	Reader(ReaderError),
+	/// {0:?} includes itself
+	Cycle(String),
+	/// Include should have a single string argument and nothing else
+	BadInclude,
+}
+impl<E: fmt::Debug + fmt::Display> Error for IncludeError<E> {}
+
+/// A span in a specific file.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileSpan {
+	/// Name of the file, see [`Source::name`].
+	pub file: String,
+	/// Byte range in the file.
+	pub span: Range<usize>,
+}
+
+/// A [`Document`] with includes resolved, along with the spans of all of its
+/// parts, like [`SpannedDocument`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct IncludedDocument {
+	/// The document.
+	pub document: Document,
+	/// Spans of the document's parts, in the file given by
+	/// [`file`](Self::file).
+	pub spans: DocumentSpans,
+	/// Names of every file read, once each, starting with the root file.
+	pub files: Vec<String>,
+	/// Files of the document's nodes, in the same shape.
+	pub node_files: Vec<NodeFiles>,
+}
+
+impl IncludedDocument {
+	/// Name of the file a node came from, by its index at each depth, see
+	/// [`SpannedDocument::node`].
+	pub fn file(&self, path: &[usize]) -> Option<&str> {
+		let (&first, rest) = path.split_first()?;
+		let mut files = self.node_files.get(first)?;
+		for &index in rest {
+			files = files.children.get(index)?;
+		}
+		Some(&self.files[files.file])
+	}
+}
+
+/// Which file a node and its children came from, see
+/// [`IncludedDocument::node_files`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct NodeFiles {
+	/// Index of the file in [`IncludedDocument::files`].
+	pub file: usize,
+	/// Files of each child node, in the same order.
+	pub children: Vec<NodeFiles>,
+}
+
+/// Resolver of include nodes.
+///
+/// ```
+/// # use std::collections::BTreeMap;
+/// # use just_kdl::dom::include::Includes;
+/// let files = BTreeMap::from([
+///     ("main.kdl", "server {\n    include \"port.kdl\"\n}"),
+///     ("port.kdl", "port 8080"),
+/// ]);
+/// let included = Includes::new(files).read("main.kdl").unwrap();
+/// assert_eq!(included.document.to_string(), "server {\n    port 8080\n}");
+/// assert_eq!(included.file(&[0, 0]), Some("port.kdl"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Includes<R> {
+	resolver: R,
+	name: SmolStr,
+}
+
+impl<R: Resolver> Includes<R> {
+	/// Create a new include resolver, for nodes named `include`.
+	pub fn new(resolver: R) -> Self {
+		Self {
+			resolver,
+			name: SmolStr::new_static("include"),
+		}
+	}
+	/// Change the name of include nodes.
+	pub fn set_name<T: Into<SmolStr>>(&mut self, name: T) { self.name = name.into(); }
+	/// Read the root file at `path`, and every file it includes.
+	///
+	/// # Errors
+	/// On syntax errors, invalid include nodes, include cycles, or if the
+	/// resolver can't find a file.
+	pub fn read(
+		&mut self,
+		path: &str,
+	) -> Result<IncludedDocument, (IncludeError<R::Error>, FileSpan)> {
+		let mut pass = Pass {
+			includes: self,
+			files: Vec::new(),
+			stack: Vec::new(),
+		};
+		let (nodes, spans, node_files) = pass.file(None, path, 0..0)?;
+		Ok(IncludedDocument {
+			document: Document { nodes },
+			spans: DocumentSpans { nodes: spans },
+			files: pass.files,
+			node_files,
+		})
+	}
+}
+
+type PassResult<T, E> = Result<T, (IncludeError<E>, FileSpan)>;
+/// nodes, with their spans and files
+type PassNodes = (Vec<Node>, Vec<NodeSpans>, Vec<NodeFiles>);
+
+/// State of a single [`Includes::read`].
+struct Pass<'inc, R> {
+	includes: &'inc mut Includes<R>,
+	files: Vec<String>,
+	/// files currently being read
+	stack: Vec<usize>,
+}
+
+impl<R: Resolver> Pass<'_, R> {
+	/// Read a file included from the file `from` at `span`.
+	fn file(
+		&mut self,
+		from: Option<usize>,
+		path: &str,
+		span: Range<usize>,
+	) -> PassResult<PassNodes, R::Error> {
+		let at = |files: &[String]| FileSpan {
+			file: from.map_or(path, |from| &files[from]).into(),
+			span: span.clone(),
+		};
+		let from_name = from.map(|from| &*self.files[from]);
+		let source = match self.includes.resolver.resolve(from_name, path) {
+			Ok(source) => source,
+			Err(err) => return Err((IncludeError::Resolve(path.into(), err), at(&self.files))),
+		};
+		// files included more than once (e.g. diamonds) share an index
+		let existing = self.files.iter().position(|file| *file == source.name);
+		if existing.is_some_and(|index| self.stack.contains(&index)) {
+			return Err((IncludeError::Cycle(source.name), at(&self.files)));
+		}
+		let read = SpannedDocument::read(Reader::new(&*source.text));
+		let SpannedDocument { document, spans } = read.map_err(|(err, err_span)| {
+			let file = source.name.clone();
+			(IncludeError::Reader(err), FileSpan {
+				file,
+				span: err_span,
+			})
+		})?;
+		let index = existing.unwrap_or_else(|| {
+			self.files.push(source.name);
+			self.files.len() - 1
+		});
+		self.stack.push(index);
+		let result = self.nodes(index, document.nodes, spans.nodes)?;
+		self.stack.pop();
+		Ok(result)
+	}
+	/// Resolve the includes in a list of nodes from `file`.
+	fn nodes(
+		&mut self,
+		file: usize,
+		nodes: Vec<Node>,
+		spans: Vec<NodeSpans>,
+	) -> PassResult<PassNodes, R::Error> {
+		let mut out_nodes = Vec::with_capacity(nodes.len());
+		let mut out_spans = Vec::with_capacity(spans.len());
+		let mut out_files = Vec::with_capacity(nodes.len());
+		for (mut node, mut node_spans) in nodes.into_iter().zip(spans) {
+			if node.name == self.includes.name {
+				let [
+					Entry {
+						name: None,
+						value: Value::String(path),
+						..
+					},
+				] = &*node.entries
+				else {
+					return Err(self.bad_include(file, node_spans));
+				};
+				if node.children.is_some() {
+					return Err(self.bad_include(file, node_spans));
+				}
+				let (included, included_spans, included_files) =
+					self.file(Some(file), path, node_spans.node)?;
+				out_nodes.extend(included);
+				out_spans.extend(included_spans);
+				out_files.extend(included_files);
+				continue;
+			}
+			let mut node_files = NodeFiles {
+				file,
+				children: Vec::new(),
+			};
+			if let (Some(children), Some(child_spans)) =
+				(&mut node.children, &mut node_spans.children)
+			{
+				(children.nodes, child_spans.nodes, node_files.children) = self.nodes(
+					file,
+					take(&mut children.nodes),
+					take(&mut child_spans.nodes),
+				)?;
+			}
+			out_nodes.push(node);
+			out_spans.push(node_spans);
+			out_files.push(node_files);
+		}
+		Ok((out_nodes, out_spans, out_files))
+	}
+	fn bad_include(&self, file: usize, spans: NodeSpans) -> (IncludeError<R::Error>, FileSpan) {
+		let file = self.files[file].clone();
+		(IncludeError::BadInclude, FileSpan {
+			file,
+			span: spans.node,
+		})
+	}
+}
@@ -0,0 +1,153 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Conversions to and from the [`kdl`] crate's types.
+//!
+//! Every [`Document`], [`Node`], [`Entry`], and [`Value`] converts to and from
+//! its `kdl` equivalent with [`From`], by reference or by value. Formatting,
+//! comments, and spans aren't kept.
+//!
+//! Numbers convert by value: integers that fit in an [`i128`] become
+//! [`KdlValue::Integer`], all other numbers become [`KdlValue::Float`] with
+//! [`Number::coerce_to_f64`], so very large integers lose precision or become
+//! infinite. Going the other way, integers are written in decimal, since `kdl`
+//! doesn't keep the original base.
+//!
+//! ```
+//! # use just_kdl::dom::Document;
+//! # use just_kdl::reader::Reader;
+//! let text = "(ip)server port=0x1F90 {\n    weight 0.5\n}";
+//! let document = Reader::new(text.as_bytes()).collect::<Result<Document, _>>().unwrap();
+//! let kdl_document = kdl::KdlDocument::from(&document);
+//! assert_eq!(kdl_document.nodes()[0].get("port"), Some(&kdl::KdlValue::Integer(8080)));
+//! assert_eq!(Document::from(&kdl_document).to_string(), "(ip)server port=8080 {\n    weight 0.5\n}");
+//! ```
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use kdl::{KdlDocument, KdlEntry, KdlIdentifier, KdlNode, KdlValue};
+use smol_str::SmolStr;
+
+use super::number::Description;
+use super::{Document, Entry, Node, Number, Value};
+
+/// `From<Owned>` implementations by reference.
+macro_rules! by_value {
+	($($from:ty => $to:ty),* $(,)?) => {$(
+		impl From<$from> for $to {
+			fn from(value: $from) -> Self { Self::from(&value) }
+		}
+	)*};
+}
+
+by_value!(
+	KdlDocument => Document,
+	KdlNode => Node,
+	KdlEntry => Entry,
+	KdlValue => Value,
+	Document => KdlDocument,
+	Node => KdlNode,
+	Entry => KdlEntry,
+	Value => KdlValue,
+);
+
+fn ident(ident: &KdlIdentifier) -> SmolStr { ident.value().into() }
+
+impl From<&KdlDocument> for Document {
+	fn from(document: &KdlDocument) -> Self {
+		Self {
+			nodes: document.nodes().iter().map(Node::from).collect(),
+		}
+	}
+}
+
+impl From<&KdlNode> for Node {
+	fn from(node: &KdlNode) -> Self {
+		Self {
+			r#type: node.ty().map(ident),
+			name: ident(node.name()),
+			entries: node.entries().iter().map(Entry::from).collect(),
+			children: node.children().map(Document::from),
+		}
+	}
+}
+
+impl From<&KdlEntry> for Entry {
+	fn from(entry: &KdlEntry) -> Self {
+		Self {
+			name: entry.name().map(ident),
+			r#type: entry.ty().map(ident),
+			value: entry.value().into(),
+		}
+	}
+}
+
+impl From<&KdlValue> for Value {
+	fn from(value: &KdlValue) -> Self {
+		match value {
+			KdlValue::String(text) => Self::String(text.into()),
+			KdlValue::Integer(value) => Self::Number((*value).into()),
+			KdlValue::Float(value) => Self::Number((*value).into()),
+			KdlValue::Bool(value) => Self::Bool(*value),
+			KdlValue::Null => Self::Null,
+		}
+	}
+}
+
+impl From<&Document> for KdlDocument {
+	fn from(document: &Document) -> Self {
+		let mut out = Self::new();
+		*out.nodes_mut() = document.nodes.iter().map(KdlNode::from).collect();
+		out
+	}
+}
+
+impl From<&Node> for KdlNode {
+	fn from(node: &Node) -> Self {
+		let mut out = Self::new(&*node.name);
+		if let Some(r#type) = &node.r#type {
+			out.set_ty(&**r#type);
+		}
+		*out.entries_mut() = node.entries.iter().map(KdlEntry::from).collect::<Vec<_>>();
+		if let Some(children) = &node.children {
+			out.set_children(children.into());
+		}
+		out
+	}
+}
+
+impl From<&Entry> for KdlEntry {
+	fn from(entry: &Entry) -> Self {
+		let value = KdlValue::from(&entry.value);
+		let mut out = match &entry.name {
+			Some(name) => Self::new_prop(&**name, value),
+			None => Self::new(value),
+		};
+		if let Some(r#type) = &entry.r#type {
+			out.set_ty(&**r#type);
+		}
+		out
+	}
+}
+
+impl From<&Value> for KdlValue {
+	fn from(value: &Value) -> Self {
+		match value {
+			Value::String(text) => Self::String(String::from(&**text)),
+			Value::Number(number) => number.into(),
+			Value::Bool(value) => Self::Bool(*value),
+			Value::Null => Self::Null,
+		}
+	}
+}
+
+impl From<&Number> for KdlValue {
+	fn from(number: &Number) -> Self {
+		match number.describe() {
+			Description::Integer { .. } => i128::try_from(number)
+				.map_or_else(|_| Self::Float(number.coerce_to_f64()), Self::Integer),
+			Description::Float { .. } | Description::Special { .. } => {
+				Self::Float(number.coerce_to_f64())
+			}
+		}
+	}
+}
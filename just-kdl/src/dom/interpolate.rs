@@ -0,0 +1,325 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Interpolating `${name}` references in string values.
+//!
+//! References have the form:
+//! - `${name}`, replaced by the value of the variable `name`.
+//! - `${name:-default}`, or `default` (which may contain references) if `name`
+//!   isn't defined.
+//! - `$${`, escaping a literal `${`.
+//!
+//! Variables come from a [`Variables`] source, like the environment, a map,
+//! or other nodes of the document by path. String variables are interpolated
+//! as well, and reference cycles are reported as errors. A string value
+//! that's just a single reference takes the variable's value as-is, so
+//! numbers and booleans keep their type.
+//!
+//! You probably want to start at [`Interpolator`].
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::error::Error;
+use core::fmt::{self, Write};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::env::var;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+use displaydoc::Display;
+
+use super::{Document, Node, Value};
+
+/// A source of variables.
+pub trait Variables {
+	/// Get the value of the variable `name`, or `None` if it isn't defined.
+	fn get(&mut self, name: &str) -> Option<Value>;
+}
+
+/// Variables by name.
+impl<K: Borrow<str> + Ord, V: Clone + Into<Value>> Variables for BTreeMap<K, V> {
+	fn get(&mut self, name: &str) -> Option<Value> {
+		BTreeMap::get(self, name).cloned().map(Into::into)
+	}
+}
+
+/// Variables by name.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+impl<K: Borrow<str> + Eq + Hash, V: Clone + Into<Value>, S: BuildHasher> Variables
+	for HashMap<K, V, S>
+{
+	fn get(&mut self, name: &str) -> Option<Value> {
+		HashMap::get(self, name).cloned().map(Into::into)
+	}
+}
+
+/// Variables from the first source that defines them.
+impl<A: Variables, B: Variables> Variables for (A, B) {
+	fn get(&mut self, name: &str) -> Option<Value> { self.0.get(name).or_else(|| self.1.get(name)) }
+}
+
+/// Environment variables, as strings.
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct EnvVariables;
+
+#[cfg(feature = "std")]
+impl Variables for EnvVariables {
+	fn get(&mut self, name: &str) -> Option<Value> { var(name).ok().map(Value::from) }
+}
+
+/// Values of other nodes, by a `.`-separated path of node names.
+///
+/// Each name picks the first node with that name, starting from the
+/// top-level nodes, and the value is the first argument of the last node.
+/// The last name can instead pick a property of the node before it, e.g.
+/// `server.listen.port` is the `port` property of `listen`, if `listen` has
+/// no child named `port`.
+#[derive(Debug, Clone, Copy)]
+pub struct NodeVariables<'doc>(pub &'doc Document);
+
+impl Variables for NodeVariables<'_> {
+	fn get(&mut self, name: &str) -> Option<Value> {
+		let mut names = name.split('.');
+		let mut node = self.0.get(names.next()?).next()?;
+		while let Some(next) = names.next() {
+			let child = node
+				.children
+				.as_ref()
+				.and_then(|children| children.get(next).next());
+			match child {
+				Some(child) => node = child,
+				None if names.next().is_none() => {
+					return node.entry(next).map(|entry| entry.value.clone());
+				}
+				None => return None,
+			}
+		}
+		node.entry(0).map(|entry| entry.value.clone())
+	}
+}
+
+/// Location of an entry in a document.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntryPath {
+	/// Index of the node at each depth, see
+	/// [`SpannedDocument::node`](super::spanned::SpannedDocument::node).
+	pub nodes: Vec<usize>,
+	/// Index of the entry in [`Node::entries`].
+	pub entry: usize,
+	/// Readable path of node names, and the property name or argument index,
+	/// like `server.listen.port` or `server.listen[0]`.
+	pub text: String,
+}
+
+impl fmt::Display for EntryPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { f.write_str(&self.text) }
+}
+
+/// An error while interpolating.
+#[derive(Debug, Display, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum InterpolateError {
+	/// Undefined variable {0:?} in {1}
+	Undefined(String, EntryPath),
+	/// Reference cycle through {0:?} in {1}
+	Cycle(String, EntryPath),
+	/// Unclosed reference in {0}
+	Unclosed(EntryPath),
+}
+impl Error for InterpolateError {}
+
+impl InterpolateError {
+	fn path_mut(&mut self) -> &mut EntryPath {
+		match self {
+			InterpolateError::Undefined(_, path)
+			| InterpolateError::Cycle(_, path)
+			| InterpolateError::Unclosed(path) => path,
+		}
+	}
+}
+
+/// Why a reference couldn't be resolved, before knowing where.
+enum Failure {
+	Undefined(String),
+	Cycle(String),
+	Unclosed,
+}
+
+/// Interpolator of string values in documents.
+///
+/// ```
+/// # use std::collections::BTreeMap;
+/// # use just_kdl::dom::Document;
+/// # use just_kdl::dom::interpolate::{Interpolator, NodeVariables};
+/// # use just_kdl::reader::Reader;
+/// let text = "defaults port=8080\nserver port=\"${defaults.port}\" host=\"${HOST:-localhost}:${defaults.port}\"";
+/// let document = Reader::new(text.as_bytes()).collect::<Result<Document, _>>().unwrap();
+/// let variables = (NodeVariables(&document), BTreeMap::<String, String>::new());
+/// let interpolated = Interpolator::new(variables).interpolate(&document).unwrap();
+/// assert_eq!(interpolated.nodes[1].to_string(), "server port=8080 host=localhost:8080");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interpolator<V> {
+	variables: V,
+	/// variables currently being interpolated
+	stack: Vec<String>,
+}
+
+impl<V: Variables> Interpolator<V> {
+	/// Create a new interpolator with a source of variables.
+	pub fn new(variables: V) -> Self {
+		Self {
+			variables,
+			stack: Vec::new(),
+		}
+	}
+	/// Interpolate every string value in a document, returning the result.
+	///
+	/// # Errors
+	/// On undefined variables, reference cycles, or unclosed references.
+	pub fn interpolate(&mut self, document: &Document) -> Result<Document, InterpolateError> {
+		let mut out = document.clone();
+		let mut path = Vec::new();
+		self.nodes(&mut out.nodes, &mut path)?;
+		Ok(out)
+	}
+	fn nodes(&mut self, nodes: &mut [Node], path: &mut Vec<usize>) -> Result<(), InterpolateError> {
+		for (index, node) in nodes.iter_mut().enumerate() {
+			path.push(index);
+			for entry_index in 0..node.entries.len() {
+				let Value::String(text) = &node.entries[entry_index].value else {
+					continue;
+				};
+				if !text.contains('$') {
+					continue;
+				}
+				self.stack.clear();
+				match self.text(text) {
+					Ok(value) => node.entries[entry_index].value = value,
+					Err(failure) => {
+						let path = entry_path(node, path, entry_index);
+						return Err(match failure {
+							Failure::Undefined(name) => InterpolateError::Undefined(name, path),
+							Failure::Cycle(name) => InterpolateError::Cycle(name, path),
+							Failure::Unclosed => InterpolateError::Unclosed(path),
+						});
+					}
+				}
+			}
+			if let Some(children) = &mut node.children {
+				self.nodes(&mut children.nodes, path).map_err(|mut err| {
+					err.path_mut()
+						.text
+						.insert_str(0, &format!("{}.", node.name));
+					err
+				})?;
+			}
+			path.pop();
+		}
+		Ok(())
+	}
+	/// interpolate a string
+	fn text(&mut self, text: &str) -> Result<Value, Failure> {
+		// just a reference keeps the type of its value
+		if let Some(reference) = text
+			.strip_prefix("${")
+			.and_then(|after| after.strip_suffix('}'))
+			&& reference_end(reference).is_none()
+		{
+			return self.reference(reference);
+		}
+		let mut out = String::new();
+		let mut rest = text;
+		while let Some(start) = rest.find('$') {
+			out.push_str(&rest[..start]);
+			rest = &rest[start..];
+			if let Some(after) = rest.strip_prefix("$${") {
+				out.push_str("${");
+				rest = after;
+			} else if let Some(after) = rest.strip_prefix("${") {
+				let end = reference_end(after).ok_or(Failure::Unclosed)?;
+				let value = self.reference(&after[..end])?;
+				rest = &after[end + 1..];
+				// writing to a string never fails
+				write_value(&mut out, &value).unwrap_or_else(|_| unreachable!());
+			} else {
+				out.push('$');
+				rest = &rest[1..];
+			}
+		}
+		out.push_str(rest);
+		Ok(Value::String(out.into()))
+	}
+	/// resolve a single reference, the text between `${` and `}`
+	fn reference(&mut self, reference: &str) -> Result<Value, Failure> {
+		let (name, default) = match reference.split_once(":-") {
+			Some((name, default)) => (name, Some(default)),
+			None => (reference, None),
+		};
+		if self.stack.iter().any(|open| open == name) {
+			return Err(Failure::Cycle(name.into()));
+		}
+		match self.variables.get(name) {
+			Some(Value::String(text)) => {
+				self.stack.push(name.into());
+				let value = self.text(&text)?;
+				self.stack.pop();
+				Ok(value)
+			}
+			Some(value) => Ok(value),
+			None => match default {
+				Some(default) => self.text(default),
+				None => Err(Failure::Undefined(name.into())),
+			},
+		}
+	}
+}
+
+/// Index of the `}` closing a reference, allowing nested references.
+fn reference_end(text: &str) -> Option<usize> {
+	let mut depth = 0_usize;
+	for (index, byte) in text.bytes().enumerate() {
+		match byte {
+			b'{' => depth += 1,
+			b'}' if depth == 0 => return Some(index),
+			b'}' => depth -= 1,
+			_ => {}
+		}
+	}
+	None
+}
+
+/// value as interpolated text
+fn write_value<W: Write>(out: &mut W, value: &Value) -> fmt::Result {
+	match value {
+		Value::String(text) => out.write_str(text),
+		Value::Number(number) => write!(out, "{number}"),
+		Value::Bool(value) => write!(out, "{value}"),
+		Value::Null => out.write_str("null"),
+	}
+}
+
+/// Locate the entry `entry` of `node` at `path`, the names of its parents
+/// are added as the error returns.
+fn entry_path(node: &Node, path: &[usize], entry: usize) -> EntryPath {
+	let text = if let Some(name) = &node.entries[entry].name {
+		format!("{}.{name}", node.name)
+	} else {
+		let index = node.entries[..entry]
+			.iter()
+			.filter(|entry| entry.name.is_none())
+			.count();
+		format!("{}[{index}]", node.name)
+	};
+	EntryPath {
+		nodes: path.into(),
+		entry,
+		text,
+	}
+}
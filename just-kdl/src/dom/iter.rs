@@ -1,13 +1,14 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //! Iterator types.
 
+use alloc::vec;
 use alloc::vec::{IntoIter as VecIter, Vec};
 use core::slice::{Iter as SliceIter, from_ref as slice_from_ref};
 
-use crate::dom::{Document, Entry, Event, Node};
+use crate::dom::{Document, Entry, Event, EventRef, Node};
 use crate::reader::Spanned;
 
-/// Build a document from an [`Event`] stream.
+/// Build a document from an [`Event`] or [`EventRef`] stream.
 ///
 /// This is a push-based builder, for a pull-based use (e.g. taking from a
 /// [`Reader`]), see the [`FromIterator`] implementations on [`Node`] and
@@ -31,8 +32,8 @@ impl DocumentBuilder {
 	/// # Panics
 	/// On an invalid event.
 	#[expect(clippy::unwrap_in_result, reason = "option is not success information")]
-	pub fn push(&mut self, event: Event) -> Option<Node> {
-		match event {
+	pub fn push<E: Into<Event>>(&mut self, event: E) -> Option<Node> {
+		match event.into() {
 			Event::Node { r#type, name } => self.stack.push(Node {
 				r#type,
 				name,
@@ -92,24 +93,43 @@ impl FromIterator<Spanned<Event>> for Node {
 	}
 }
 
+/// Assumes event stream is valid.
+impl<'doc> FromIterator<EventRef<'doc>> for Document {
+	fn from_iter<T: IntoIterator<Item = EventRef<'doc>>>(iter: T) -> Self {
+		iter.into_iter().map(Event::from).collect()
+	}
+}
+/// Assumes event stream is valid, only takes first node from iterator.
+impl<'doc> FromIterator<EventRef<'doc>> for Node {
+	fn from_iter<T: IntoIterator<Item = EventRef<'doc>>>(iter: T) -> Self {
+		iter.into_iter().map(Event::from).collect()
+	}
+}
+
 // owned / borrowed has different iterator (and iterator value) types
 trait GenericIterKind: Sized {
 	type Node;
 	type Entry;
+	type Event;
 	type NodeIter: Iterator<Item = Self::Node>;
 	type EntryIter: Iterator<Item = Self::Entry>;
-	fn entry_event(entry: Self::Entry) -> Event;
+	const CHILDREN: Self::Event;
+	const END: Self::Event;
+	fn entry_event(entry: Self::Entry) -> Self::Event;
 	fn document_iter(doc: Self) -> Self::NodeIter;
 	fn node_iter(node: Self::Node) -> Self::NodeIter;
-	fn node_event(node: Self::Node) -> (Event, Self::EntryIter, Option<Self>);
+	fn node_event(node: Self::Node) -> (Self::Event, Self::EntryIter, Option<Self>);
 }
 
 /// owned case
 impl GenericIterKind for Document {
 	type Node = Node;
 	type Entry = Entry;
+	type Event = Event;
 	type NodeIter = VecIter<Node>;
 	type EntryIter = VecIter<Entry>;
+	const CHILDREN: Event = Event::Children;
+	const END: Event = Event::End;
 	fn entry_event(entry: Self::Entry) -> Event { Event::Entry(entry) }
 	fn document_iter(doc: Self) -> Self::NodeIter { doc.nodes.into_iter() }
 	fn node_iter(node: Self::Node) -> Self::NodeIter { vec![node].into_iter() }
@@ -128,17 +148,20 @@ impl GenericIterKind for Document {
 impl<'doc> GenericIterKind for &'doc Document {
 	type Node = &'doc Node;
 	type Entry = &'doc Entry;
+	type Event = EventRef<'doc>;
 	type NodeIter = SliceIter<'doc, Node>;
 	type EntryIter = SliceIter<'doc, Entry>;
-	fn entry_event(entry: Self::Entry) -> Event { Event::Entry(entry.clone()) }
+	const CHILDREN: EventRef<'doc> = EventRef::Children;
+	const END: EventRef<'doc> = EventRef::End;
+	fn entry_event(entry: Self::Entry) -> Self::Event { EventRef::Entry(entry) }
 	fn document_iter(doc: Self) -> Self::NodeIter { doc.nodes.iter() }
 	fn node_iter(node: Self::Node) -> Self::NodeIter { slice_from_ref(node).iter() }
-	fn node_event(node: Self::Node) -> (Event, Self::EntryIter, Option<Self>) {
+	fn node_event(node: Self::Node) -> (Self::Event, Self::EntryIter, Option<Self>) {
 		let entries = node.entries.iter();
 		let children = node.children.as_ref();
-		let r#type = node.r#type.clone();
-		let name = node.name.clone();
-		(Event::Node { r#type, name }, entries, children)
+		let r#type = node.r#type.as_ref();
+		let name = &node.name;
+		(EventRef::Node { r#type, name }, entries, children)
 	}
 }
 
@@ -159,7 +182,7 @@ impl<K: GenericIterKind> GenericIter<K> {
 }
 
 impl<K: GenericIterKind> Iterator for GenericIter<K> {
-	type Item = Event;
+	type Item = K::Event;
 	fn next(&mut self) -> Option<Self::Item> {
 		if let Some((entries, children)) = &mut self.top {
 			// currently in a node
@@ -168,10 +191,10 @@ impl<K: GenericIterKind> Iterator for GenericIter<K> {
 			} else if let Some(nodes) = children.take() {
 				self.stack.push(K::document_iter(nodes));
 				self.top = None;
-				Some(Event::Children)
+				Some(K::CHILDREN)
 			} else {
 				self.top = None;
-				Some(Event::End)
+				Some(K::END)
 			}
 		} else if let Some(iter) = self.stack.last_mut() {
 			// currently in a document
@@ -182,7 +205,7 @@ impl<K: GenericIterKind> Iterator for GenericIter<K> {
 			} else {
 				self.stack.pop();
 				// final stack item needs no End event
-				(!self.stack.is_empty()).then_some(Event::End)
+				(!self.stack.is_empty()).then_some(K::END)
 			}
 		} else {
 			// done
@@ -211,22 +234,39 @@ impl IntoIterator for Node {
 	fn into_iter(self) -> Self::IntoIter { IntoIter(GenericIter::from_node(self)) }
 }
 
-/// Borrowing iterator over a [`Document`] or [`Node`].
-pub struct Iter<'doc>(GenericIter<&'doc Document>);
+/// Borrowing iterator over a [`Document`] or [`Node`], cloning into owned
+/// [`Event`]s.
+pub struct Iter<'doc>(RefIter<'doc>);
 
 impl Iterator for Iter<'_> {
 	type Item = Event;
-	fn next(&mut self) -> Option<Self::Item> { self.0.next() }
+	fn next(&mut self) -> Option<Self::Item> { self.0.next().map(Event::from) }
 }
 
 impl<'doc> IntoIterator for &'doc Document {
 	type Item = Event;
 	type IntoIter = Iter<'doc>;
-	fn into_iter(self) -> Self::IntoIter { Iter(GenericIter::from_document(self)) }
+	fn into_iter(self) -> Self::IntoIter { Iter(RefIter::from_document(self)) }
 }
 
 impl<'doc> IntoIterator for &'doc Node {
 	type Item = Event;
 	type IntoIter = Iter<'doc>;
-	fn into_iter(self) -> Self::IntoIter { Iter(GenericIter::from_node(self)) }
+	fn into_iter(self) -> Self::IntoIter { Iter(RefIter::from_node(self)) }
+}
+
+/// Borrowing iterator over a [`Document`] or [`Node`], without cloning, see
+/// [`Document::iter_ref`].
+pub struct RefIter<'doc>(GenericIter<&'doc Document>);
+
+impl<'doc> RefIter<'doc> {
+	pub(super) fn from_document(doc: &'doc Document) -> Self {
+		Self(GenericIter::from_document(doc))
+	}
+	pub(super) fn from_node(node: &'doc Node) -> Self { Self(GenericIter::from_node(node)) }
+}
+
+impl<'doc> Iterator for RefIter<'doc> {
+	type Item = EventRef<'doc>;
+	fn next(&mut self) -> Option<Self::Item> { self.0.next() }
 }
@@ -157,7 +157,8 @@ macro_rules! quick_impl {
 		impl From<$ty> for Number {
 			fn from(v: $ty) -> Self {
 				match v.classify() {
-					FpCategory::Nan | FpCategory::Infinite => Self(format_smolstr!("#{v:?}")),
+					FpCategory::Nan => Self::NAN,
+					FpCategory::Infinite => Self(format_smolstr!("#{v:?}")),
 					_ => Self(format_smolstr!("f{v:?}")),
 				}
 			}
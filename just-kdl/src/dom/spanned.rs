@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Documents that remember where their parts came from.
+//!
+//! Spans are kept in a tree of the same shape next to the [`Document`], so
+//! application-level checks can point errors at the source.
+//!
+//! You probably want to start at [`SpannedDocument`].
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::dom::iter::DocumentBuilder;
+use crate::dom::{Document, Entry, EntryKey, Event, Node};
+use crate::lexer::Input;
+use crate::reader::{Reader, ReaderError, Spanned};
+
+/// Spans of a [`Document`]'s nodes, in the same order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DocumentSpans {
+	/// Spans of each node.
+	pub nodes: Vec<NodeSpans>,
+}
+
+/// Spans of a [`Node`]'s parts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NodeSpans {
+	/// The whole node, up to and including its terminator.
+	pub node: Range<usize>,
+	/// Type annotation, including parentheses.
+	pub r#type: Option<Range<usize>>,
+	/// Name.
+	pub name: Range<usize>,
+	/// Spans of each entry, in the same order.
+	pub entries: Vec<EntrySpans>,
+	/// Spans of the child document.
+	pub children: Option<DocumentSpans>,
+}
+
+impl NodeSpans {
+	/// Get the spans of a specific entry of `node`, see [`Node::entry`].
+	pub fn entry<'key, T: Into<EntryKey<'key>>>(&self, node: &Node, key: T) -> Option<&EntrySpans> {
+		let (index, _) = key
+			.into()
+			.seek(node.entries.iter().enumerate(), |(_, ent)| {
+				ent.name.as_deref()
+			})?;
+		self.entries.get(index)
+	}
+	/// Iterate over the entries of `node` and their spans.
+	pub fn entries<'node>(
+		&'node self,
+		node: &'node Node,
+	) -> impl Iterator<Item = (&'node Entry, &'node EntrySpans)> {
+		node.entries.iter().zip(&self.entries)
+	}
+}
+
+/// Spans of an [`Entry`]'s parts.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntrySpans {
+	/// The whole entry.
+	pub entry: Range<usize>,
+	/// Type annotation, including parentheses.
+	pub r#type: Option<Range<usize>>,
+	/// Property name.
+	pub name: Option<Range<usize>>,
+	/// Value.
+	pub value: Range<usize>,
+}
+
+/// A [`Document`] along with the spans of all of its parts.
+///
+/// ```
+/// # use just_kdl::dom::spanned::SpannedDocument;
+/// # use just_kdl::reader::Reader;
+/// let text = "server {\n    port 70000\n}";
+/// let document = SpannedDocument::read(Reader::new(text.as_bytes())).unwrap();
+/// let (port, spans) = document.node(&[0, 0]).unwrap();
+/// let value = spans.entry(port, 0).unwrap().value.clone();
+/// assert_eq!(&text[value], "70000");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SpannedDocument {
+	/// The document.
+	pub document: Document,
+	/// Spans of the document's parts.
+	pub spans: DocumentSpans,
+}
+
+impl SpannedDocument {
+	/// Read an entire document.
+	///
+	/// # Errors
+	/// On any syntax errors.
+	pub fn read<T: Input>(mut reader: Reader<T>) -> Result<Self, Spanned<ReaderError>> {
+		let mut builder = DocumentBuilder::new();
+		let mut document = Document::new();
+		let mut spans = DocumentSpans::default();
+		let mut stack = Vec::<NodeSpans>::new();
+		while let Some(result) = reader.next() {
+			let (event, span) = result?;
+			let parts = reader.parts();
+			// reader guarantees a valid event stream
+			match &event {
+				Event::Node { .. } => stack.push(NodeSpans {
+					node: span.clone(),
+					r#type: parts.r#type.clone(),
+					name: parts.name.clone().unwrap_or(span),
+					entries: Vec::new(),
+					children: None,
+				}),
+				Event::Entry(_) => {
+					if let Some(node) = stack.last_mut() {
+						node.entries.push(EntrySpans {
+							entry: span.clone(),
+							r#type: parts.r#type.clone(),
+							name: parts.name.clone(),
+							value: parts.value.clone().unwrap_or(span),
+						});
+					}
+				}
+				Event::Children => {
+					if let Some(node) = stack.last_mut() {
+						node.children = Some(DocumentSpans::default());
+					}
+				}
+				Event::End => {
+					if let Some(mut finished) = stack.pop() {
+						finished.node.end = span.end;
+						match stack.last_mut() {
+							Some(parent) => parent.children.get_or_insert_default(),
+							None => &mut spans,
+						}
+						.nodes
+						.push(finished);
+					}
+				}
+			}
+			document.nodes.extend(builder.push(event));
+		}
+		Ok(Self { document, spans })
+	}
+	/// Get a node and its spans by its index at each depth.
+	pub fn node(&self, path: &[usize]) -> Option<(&Node, &NodeSpans)> {
+		let (&first, rest) = path.split_first()?;
+		let mut node = self.document.nodes.get(first)?;
+		let mut spans = self.spans.nodes.get(first)?;
+		for &index in rest {
+			node = node.children.as_ref()?.nodes.get(index)?;
+			spans = spans.children.as_ref()?.nodes.get(index)?;
+		}
+		Some((node, spans))
+	}
+	/// Iterate over top-level nodes and their spans.
+	pub fn nodes(&self) -> impl Iterator<Item = (&Node, &NodeSpans)> {
+		self.document.nodes.iter().zip(&self.spans.nodes)
+	}
+}
@@ -0,0 +1,219 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Traversing documents with enter/exit hooks.
+//!
+//! Nodes are visited depth-first, in document order:
+//! 1. [`enter_node`](Visitor::enter_node)
+//! 2. [`entry`](Visitor::entry) for each entry
+//! 3. [`enter_children`](Visitor::enter_children), the child nodes, and
+//!    [`exit_children`](Visitor::exit_children), if the node has children
+//! 4. [`exit_node`](Visitor::exit_node)
+//!
+//! Enter hooks can skip the rest of a subtree, exit hooks are still called.
+//! Every hook gets the [`Path`] to the current node.
+//!
+//! You probably want to start at [`Visitor`] or [`VisitorMut`].
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use smol_str::SmolStr;
+
+use super::{Document, Entry, Node};
+
+/// Whether to visit the inside of a node or child document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Flow {
+	/// Visit everything inside.
+	#[default]
+	Continue,
+	/// Skip everything inside, straight to the exit hook.
+	Skip,
+}
+
+/// What to do with a node, for [`VisitorMut`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Edit {
+	/// Keep the node, and visit everything inside.
+	#[default]
+	Continue,
+	/// Keep the node, but skip everything inside, straight to the exit hook.
+	Skip,
+	/// Remove the node, without calling the exit hook.
+	Remove,
+	/// Replace the node with any number of nodes, which aren't visited,
+	/// without calling the exit hook.
+	Replace(Vec<Node>),
+}
+
+/// Location of a node in a document, the names and indices of it and its
+/// ancestors.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Path {
+	names: Vec<SmolStr>,
+	indices: Vec<usize>,
+}
+
+impl Path {
+	/// Names of the node and its ancestors, starting at the top-level node.
+	pub fn names(&self) -> &[SmolStr] { &self.names }
+	/// Index of the node and its ancestors in their parent, starting at the
+	/// top-level node, see
+	/// [`SpannedDocument::node`](super::spanned::SpannedDocument::node).
+	///
+	/// For [`VisitorMut`], these are indices at the time of visiting, after
+	/// any earlier siblings were removed or replaced.
+	pub fn indices(&self) -> &[usize] { &self.indices }
+	/// Number of ancestors of the node, `0` for top-level nodes.
+	pub fn depth(&self) -> usize { self.names.len().saturating_sub(1) }
+	fn push(&mut self, name: SmolStr, index: usize) {
+		self.names.push(name);
+		self.indices.push(index);
+	}
+	fn pop(&mut self) {
+		self.names.pop();
+		self.indices.pop();
+	}
+}
+
+/// Names separated by `.`, like `server.listen`.
+impl fmt::Display for Path {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut names = self.names.iter();
+		if let Some(first) = names.next() {
+			f.write_str(first)?;
+		}
+		for name in names {
+			write!(f, ".{name}")?;
+		}
+		Ok(())
+	}
+}
+
+/// Hooks for traversing a document, see [`Document::visit`].
+///
+/// Every hook does nothing by default.
+///
+/// ```
+/// # use just_kdl::dom::{Document, Node};
+/// # use just_kdl::dom::visit::{Flow, Path, Visitor};
+/// # use just_kdl::reader::Reader;
+/// /// Paths of every node, except inside `skip`
+/// struct Paths(Vec<String>);
+/// impl Visitor for Paths {
+///     fn enter_node(&mut self, node: &Node, path: &Path) -> Flow {
+///         if node.name() == "skip" {
+///             return Flow::Skip;
+///         }
+///         self.0.push(path.to_string());
+///         Flow::Continue
+///     }
+/// }
+/// let text = "a { b; skip { c } }\nd";
+/// let document = Reader::new(text.as_bytes()).collect::<Result<Document, _>>().unwrap();
+/// let mut paths = Paths(Vec::new());
+/// document.visit(&mut paths);
+/// assert_eq!(paths.0, ["a", "a.b", "d"]);
+/// ```
+pub trait Visitor {
+	/// Called before the inside of a node.
+	fn enter_node(&mut self, _node: &Node, _path: &Path) -> Flow { Flow::Continue }
+	/// Called after the inside of a node.
+	fn exit_node(&mut self, _node: &Node, _path: &Path) {}
+	/// Called for each entry of a node, with its index in
+	/// [`Node::entries`], and the path to the node.
+	fn entry(&mut self, _entry: &Entry, _index: usize, _path: &Path) {}
+	/// Called before the child nodes of a node, with the path to the node.
+	fn enter_children(&mut self, _children: &Document, _path: &Path) -> Flow { Flow::Continue }
+	/// Called after the child nodes of a node, with the path to the node.
+	fn exit_children(&mut self, _children: &Document, _path: &Path) {}
+}
+
+/// Hooks for traversing and editing a document, see [`Document::visit_mut`].
+///
+/// Like [`Visitor`], but node hooks can also remove or replace the current
+/// node. Every hook does nothing by default.
+pub trait VisitorMut {
+	/// Called before the inside of a node.
+	fn enter_node(&mut self, _node: &mut Node, _path: &Path) -> Edit { Edit::Continue }
+	/// Called after the inside of a node, where [`Edit::Skip`] is the same as
+	/// [`Edit::Continue`].
+	fn exit_node(&mut self, _node: &mut Node, _path: &Path) -> Edit { Edit::Continue }
+	/// Called for each entry of a node, with its index in
+	/// [`Node::entries`], and the path to the node.
+	fn entry(&mut self, _entry: &mut Entry, _index: usize, _path: &Path) {}
+	/// Called before the child nodes of a node, with the path to the node.
+	fn enter_children(&mut self, _children: &mut Document, _path: &Path) -> Flow { Flow::Continue }
+	/// Called after the child nodes of a node, with the path to the node.
+	fn exit_children(&mut self, _children: &mut Document, _path: &Path) {}
+}
+
+pub(super) fn nodes<V: Visitor + ?Sized>(visitor: &mut V, nodes: &[Node], path: &mut Path) {
+	for (index, node) in nodes.iter().enumerate() {
+		path.push(node.name.clone(), index);
+		if visitor.enter_node(node, path) == Flow::Continue {
+			for (entry_index, entry) in node.entries.iter().enumerate() {
+				visitor.entry(entry, entry_index, path);
+			}
+			if let Some(children) = &node.children {
+				if visitor.enter_children(children, path) == Flow::Continue {
+					self::nodes(visitor, &children.nodes, path);
+				}
+				visitor.exit_children(children, path);
+			}
+		}
+		visitor.exit_node(node, path);
+		path.pop();
+	}
+}
+
+pub(super) fn nodes_mut<V: VisitorMut + ?Sized>(
+	visitor: &mut V,
+	nodes: &mut Vec<Node>,
+	path: &mut Path,
+) {
+	let mut index = 0;
+	while let Some(node) = nodes.get_mut(index) {
+		path.push(node.name.clone(), index);
+		let edit = node_mut(visitor, node, path);
+		path.pop();
+		match edit {
+			Edit::Continue | Edit::Skip => index += 1,
+			Edit::Remove => drop(nodes.remove(index)),
+			Edit::Replace(with) => {
+				let count = with.len();
+				nodes.splice(index..=index, with);
+				index += count;
+			}
+		}
+	}
+}
+
+/// visit a single node, already in `path`
+fn node_mut<V: VisitorMut + ?Sized>(visitor: &mut V, node: &mut Node, path: &mut Path) -> Edit {
+	match visitor.enter_node(node, path) {
+		Edit::Continue => {
+			for (entry_index, entry) in node.entries.iter_mut().enumerate() {
+				visitor.entry(entry, entry_index, path);
+			}
+			if let Some(children) = &mut node.children {
+				if visitor.enter_children(children, path) == Flow::Continue {
+					nodes_mut(visitor, &mut children.nodes, path);
+				}
+				visitor.exit_children(children, path);
+			}
+		}
+		Edit::Skip => {}
+		edit @ (Edit::Remove | Edit::Replace(_)) => return edit,
+	}
+	visitor.exit_node(node, path)
+}
+
+/// [`Node::normalize`] as a visitor.
+pub(super) struct Normalize;
+
+impl VisitorMut for Normalize {
+	fn exit_node(&mut self, node: &mut Node, _: &Path) -> Edit {
+		node.normalize_shallow();
+		Edit::Continue
+	}
+}
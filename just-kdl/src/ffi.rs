@@ -0,0 +1,394 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! C ABI bindings for the streaming [`Reader`].
+//!
+//! The `just-kdl-ffi` crate builds these into shared and static libraries,
+//! alongside the matching `include/just_kdl.h` header.
+//!
+//! Strings are [`KdlStr`]s pointing into the reader, valid until the next
+//! [`kdl_reader_next`] or [`kdl_reader_free`], and aren't NUL-terminated,
+//! since KDL strings can contain NUL characters.
+//!
+//! You probably want to start at [`kdl_reader_new_buffer`] or
+//! [`kdl_reader_new_callback`].
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use core::ffi::c_void;
+use core::fmt::Write;
+use core::ops::Range;
+use core::{ptr, slice};
+use std::io::{self, BufReader, Read};
+
+use smol_str::SmolStr;
+
+use crate::dom::{Entry, Event, Value};
+use crate::lexer::{BufReadInput, Input, LexerError};
+use crate::reader::Reader;
+
+/// Borrowed UTF-8 string, with a null `ptr` if it's missing.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KdlStr {
+	/// First byte of the string.
+	pub ptr: *const u8,
+	/// Length in bytes.
+	pub len: usize,
+}
+
+impl KdlStr {
+	const NONE: Self = Self {
+		ptr: ptr::null(),
+		len: 0,
+	};
+	fn new(text: &str) -> Self {
+		Self {
+			ptr: text.as_ptr(),
+			len: text.len(),
+		}
+	}
+	fn new_opt(text: Option<&SmolStr>) -> Self { text.map_or(Self::NONE, |text| Self::new(text)) }
+}
+
+/// Byte range in the input, like [`Spanned`](crate::reader::Spanned).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KdlSpan {
+	/// First byte.
+	pub start: usize,
+	/// One past the last byte.
+	pub end: usize,
+}
+
+impl From<Range<usize>> for KdlSpan {
+	fn from(span: Range<usize>) -> Self {
+		Self {
+			start: span.start,
+			end: span.end,
+		}
+	}
+}
+
+/// Kind of a [`KdlEvent`], like [`Event`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KdlEventKind {
+	/// Start of a node, with a name and maybe a type.
+	Node,
+	/// Argument or property of the current node, with a value, and maybe a
+	/// name and type.
+	Entry,
+	/// Start of the current node's children.
+	Children,
+	/// End of the current node.
+	End,
+}
+
+/// Kind of a [`KdlValue`], like [`Value`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KdlValueKind {
+	/// String value, in `text`.
+	String,
+	/// Number value, in `text`, `integer`, and `number`.
+	Number,
+	/// `#true` or `#false`, in `boolean`.
+	Bool,
+	/// `#null`
+	Null,
+}
+
+/// Value of an entry.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KdlValue {
+	/// Which fields are used.
+	pub kind: KdlValueKind,
+	/// String contents, or number as written in KDL, like `0x10` or `#inf`.
+	pub text: KdlStr,
+	/// Boolean value.
+	pub boolean: bool,
+	/// If the number is an integer that fits in `integer`.
+	pub is_integer: bool,
+	/// Number as an integer.
+	pub integer: i64,
+	/// Number as a float, see
+	/// [`Number::coerce_to_f64`](crate::dom::Number::coerce_to_f64).
+	pub number: f64,
+}
+
+impl KdlValue {
+	const NULL: Self = Self {
+		kind: KdlValueKind::Null,
+		text: KdlStr::NONE,
+		boolean: false,
+		is_integer: false,
+		integer: 0,
+		number: 0.0,
+	};
+	/// `number` holds the text of numbers
+	fn new(value: &Value, number: &mut String) -> Self {
+		match value {
+			Value::String(text) => Self {
+				kind: KdlValueKind::String,
+				text: KdlStr::new(text),
+				..Self::NULL
+			},
+			Value::Number(value) => {
+				number.clear();
+				write!(number, "{value}").unwrap_or_else(|_| unreachable!());
+				let integer = i64::try_from(value).ok();
+				Self {
+					kind: KdlValueKind::Number,
+					text: KdlStr::new(number),
+					is_integer: integer.is_some(),
+					integer: integer.unwrap_or_default(),
+					number: value.coerce_to_f64(),
+					..Self::NULL
+				}
+			}
+			Value::Bool(value) => Self {
+				kind: KdlValueKind::Bool,
+				boolean: *value,
+				..Self::NULL
+			},
+			Value::Null => Self::NULL,
+		}
+	}
+}
+
+/// Single event, written by [`kdl_reader_next`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KdlEvent {
+	/// Which fields are used.
+	pub kind: KdlEventKind,
+	/// Type annotation of a node or entry.
+	pub r#type: KdlStr,
+	/// Name of a node or property, missing for arguments.
+	pub name: KdlStr,
+	/// Value of an entry.
+	pub value: KdlValue,
+	/// Location of the event in the input.
+	pub span: KdlSpan,
+}
+
+/// Result of [`kdl_reader_next`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KdlStatus {
+	/// An event was read.
+	Event,
+	/// The document ended.
+	Done,
+	/// There was an error, see [`kdl_reader_error`].
+	Error,
+}
+
+/// Error, written by [`kdl_reader_error`].
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct KdlError {
+	/// Description of the error.
+	pub message: KdlStr,
+	/// Suggestion for fixing the error, see
+	/// [`ReaderError::hint`](crate::reader::ReaderError::hint).
+	pub hint: KdlStr,
+	/// Location of the error in the input.
+	pub span: KdlSpan,
+}
+
+/// Read callback, for [`kdl_reader_new_callback`].
+///
+/// Called with the `user` pointer, to read up to `len` bytes into `buffer`,
+/// returning how many bytes were read, `0` at end-of-file, or a negative
+/// number on error.
+pub type KdlReadFn = unsafe extern "C" fn(user: *mut c_void, buffer: *mut u8, len: usize) -> isize;
+
+/// [`KdlReadFn`] as [`Read`]
+struct Callback {
+	read: KdlReadFn,
+	user: *mut c_void,
+}
+
+impl Read for Callback {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		// SAFETY: guaranteed by the caller of `kdl_reader_new_callback`
+		let size = unsafe { (self.read)(self.user, buf.as_mut_ptr(), buf.len()) };
+		usize::try_from(size)
+			.ok()
+			.filter(|&size| size <= buf.len())
+			.ok_or_else(|| io::Error::other("read callback failed"))
+	}
+}
+
+/// Owned buffer or read callback
+enum Source {
+	Buffer(Box<[u8]>, usize),
+	Callback(BufReadInput<BufReader<Callback>>),
+}
+
+impl Input for Source {
+	fn peek(&mut self, n: usize) -> Result<&[u8], LexerError> {
+		match self {
+			Self::Buffer(data, start) => Ok(&data[*start..]),
+			Self::Callback(input) => input.peek(n),
+		}
+	}
+	fn advance(&mut self, n: usize) {
+		match self {
+			Self::Buffer(_, start) => *start += n,
+			Self::Callback(input) => input.advance(n),
+		}
+	}
+}
+
+/// Reader handle, free it with [`kdl_reader_free`].
+pub struct KdlReader {
+	reader: Reader<Source>,
+	/// keeps the strings of the last event alive
+	event: Option<Event>,
+	/// text of the last number
+	number: String,
+	/// message and span of the last error
+	error: Option<(String, KdlError)>,
+}
+
+impl KdlReader {
+	fn new(source: Source) -> *mut Self {
+		Box::into_raw(Box::new(Self {
+			reader: Reader::new(source),
+			event: None,
+			number: String::new(),
+			error: None,
+		}))
+	}
+	fn next(&mut self, out: &mut KdlEvent) -> KdlStatus {
+		let (event, span) = match self.reader.next() {
+			None => return KdlStatus::Done,
+			Some(Err((err, span))) => {
+				let error = KdlError {
+					message: KdlStr::NONE,
+					hint: err.hint().map_or(KdlStr::NONE, KdlStr::new),
+					span: span.into(),
+				};
+				self.error = Some((err.to_string(), error));
+				return KdlStatus::Error;
+			}
+			Some(Ok(event)) => event,
+		};
+		let mut value = KdlValue::NULL;
+		let (kind, r#type, name) = match self.event.insert(event) {
+			Event::Node { r#type, name } => (KdlEventKind::Node, r#type.as_ref(), Some(&*name)),
+			Event::Entry(Entry {
+				name,
+				r#type,
+				value: entry,
+			}) => {
+				value = KdlValue::new(entry, &mut self.number);
+				(KdlEventKind::Entry, r#type.as_ref(), name.as_ref())
+			}
+			Event::Children => (KdlEventKind::Children, None, None),
+			Event::End => (KdlEventKind::End, None, None),
+		};
+		*out = KdlEvent {
+			kind,
+			r#type: KdlStr::new_opt(r#type),
+			name: KdlStr::new_opt(name),
+			value,
+			span: span.into(),
+		};
+		KdlStatus::Event
+	}
+}
+
+/// Create a reader of a copy of `len` bytes at `data`.
+///
+/// # Safety
+/// `data` must be valid for reads of `len` bytes, or `len` must be `0`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kdl_reader_new_buffer(data: *const u8, len: usize) -> *mut KdlReader {
+	let data = if len == 0 {
+		Box::default()
+	} else {
+		// SAFETY: guaranteed by caller
+		Box::from(unsafe { slice::from_raw_parts(data, len) })
+	};
+	KdlReader::new(Source::Buffer(data, 0))
+}
+
+/// Create a reader that gets its input from `read`, called with `user`.
+///
+/// # Safety
+/// `read` must be safe to call with `user` and any writable buffer, until the
+/// reader is freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kdl_reader_new_callback(
+	read: KdlReadFn,
+	user: *mut c_void,
+) -> *mut KdlReader {
+	let input = BufReadInput::new(BufReader::new(Callback { read, user }));
+	KdlReader::new(Source::Callback(input))
+}
+
+/// Read the next event into `event`, returning [`KdlStatus::Event`] if it was
+/// written.
+///
+/// After the document ends or an error, this returns [`KdlStatus::Done`].
+///
+/// # Safety
+/// `reader` must be a live reader, and `event` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kdl_reader_next(
+	reader: *mut KdlReader,
+	event: *mut KdlEvent,
+) -> KdlStatus {
+	// SAFETY: guaranteed by caller
+	let reader = unsafe { &mut *reader };
+	let mut out = KdlEvent {
+		kind: KdlEventKind::End,
+		r#type: KdlStr::NONE,
+		name: KdlStr::NONE,
+		value: KdlValue::NULL,
+		span: KdlSpan { start: 0, end: 0 },
+	};
+	let status = reader.next(&mut out);
+	if status == KdlStatus::Event {
+		// SAFETY: guaranteed by caller
+		unsafe { event.write(out) };
+	}
+	status
+}
+
+/// Write the last error into `error`, returning `false` if there wasn't one.
+///
+/// The message is valid until the reader is freed.
+///
+/// # Safety
+/// `reader` must be a live reader, and `error` must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kdl_reader_error(reader: *const KdlReader, error: *mut KdlError) -> bool {
+	// SAFETY: guaranteed by caller
+	let reader = unsafe { &*reader };
+	let Some((message, out)) = &reader.error else {
+		return false;
+	};
+	let out = KdlError {
+		message: KdlStr::new(message),
+		..*out
+	};
+	// SAFETY: guaranteed by caller
+	unsafe { error.write(out) };
+	true
+}
+
+/// Free a reader, along with its strings, doing nothing if it's null.
+///
+/// # Safety
+/// `reader` must be null, or a live reader that isn't used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn kdl_reader_free(reader: *mut KdlReader) {
+	if !reader.is_null() {
+		// SAFETY: guaranteed by caller
+		drop(unsafe { Box::from_raw(reader) });
+	}
+}
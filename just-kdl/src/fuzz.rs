@@ -0,0 +1,247 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Generating documents and checking round-trips, for fuzzing.
+//!
+//! [`Document`], [`Node`], [`Entry`], [`Value`], and [`Number`] implement
+//! [`Arbitrary`], generating numbers the same way the reader stores them, so
+//! generated documents survive being written and read again. The `assert_*`
+//! functions panic when a round-trip doesn't hold, which is what fuzzers look
+//! for:
+//! ```
+//! # use arbitrary::{Arbitrary, Unstructured};
+//! # use just_kdl::dom::Document;
+//! # use just_kdl::fuzz;
+//! let data = b"some fuzzer input, usually a lot longer than this";
+//! let document = Document::arbitrary(&mut Unstructured::new(data)).unwrap();
+//! fuzz::assert_events(&document);
+//! fuzz::assert_rewrite(&document);
+//! fuzz::assert_reread(data);
+//! ```
+//!
+//! You probably want to start at [`assert_reread`].
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use arbitrary::{Arbitrary, Error, Unstructured};
+use smol_str::SmolStr;
+
+use crate::dom::{Document, Entry, Event, Node, Number, Value};
+use crate::reader::{Reader, ReaderError, Spanned};
+use crate::writer::Writer;
+
+/// Children blocks nested deeper than this aren't generated.
+const MAX_DEPTH: usize = 8;
+
+fn string(u: &mut Unstructured) -> arbitrary::Result<SmolStr> {
+	<&str>::arbitrary(u).map(SmolStr::from)
+}
+
+fn optional_string(u: &mut Unstructured) -> arbitrary::Result<Option<SmolStr>> {
+	Ok(if u.arbitrary()? {
+		Some(string(u)?)
+	} else {
+		None
+	})
+}
+
+fn document(u: &mut Unstructured, depth: usize) -> arbitrary::Result<Document> {
+	let mut nodes = Vec::new();
+	while u.arbitrary()? {
+		nodes.push(node(u, depth)?);
+	}
+	Ok(Document { nodes })
+}
+
+fn node(u: &mut Unstructured, depth: usize) -> arbitrary::Result<Node> {
+	Ok(Node {
+		r#type: optional_string(u)?,
+		name: string(u)?,
+		entries: u.arbitrary()?,
+		children: if depth < MAX_DEPTH && u.arbitrary()? {
+			Some(document(u, depth + 1)?)
+		} else {
+			None
+		},
+	})
+}
+
+/// Push up to 32 digits of `radix`, in either case, without leading zeros
+/// for `trim` since the reader drops some of them.
+fn digits(u: &mut Unstructured, out: &mut String, radix: u32, trim: bool) -> arbitrary::Result<()> {
+	let start = out.len();
+	for _ in 0..u.int_in_range(1..=32)? {
+		let digit = char::from_digit(u.int_in_range(0..=radix - 1)?, radix).unwrap_or('0');
+		out.push(if u.arbitrary()? {
+			digit.to_ascii_uppercase()
+		} else {
+			digit
+		});
+	}
+	if trim {
+		let zeros = out[start..].len() - out[start..].trim_start_matches('0').len();
+		// keep a single zero
+		out.drain(start..start + zeros.min(out.len() - start - 1));
+	}
+	Ok(())
+}
+
+impl<'data> Arbitrary<'data> for Document {
+	fn arbitrary(u: &mut Unstructured<'data>) -> arbitrary::Result<Self> { document(u, 0) }
+}
+
+impl<'data> Arbitrary<'data> for Node {
+	fn arbitrary(u: &mut Unstructured<'data>) -> arbitrary::Result<Self> { node(u, 0) }
+}
+
+impl<'data> Arbitrary<'data> for Entry {
+	fn arbitrary(u: &mut Unstructured<'data>) -> arbitrary::Result<Self> {
+		Ok(Self {
+			name: optional_string(u)?,
+			r#type: optional_string(u)?,
+			value: u.arbitrary()?,
+		})
+	}
+}
+
+impl<'data> Arbitrary<'data> for Value {
+	fn arbitrary(u: &mut Unstructured<'data>) -> arbitrary::Result<Self> {
+		Ok(match u.int_in_range(0..=3)? {
+			0 => Self::String(string(u)?),
+			1 => Self::Number(u.arbitrary()?),
+			2 => Self::Bool(u.arbitrary()?),
+			_ => Self::Null,
+		})
+	}
+}
+
+/// Integers in every base, and decimal floats, written the way the reader
+/// stores them.
+impl<'data> Arbitrary<'data> for Number {
+	fn arbitrary(u: &mut Unstructured<'data>) -> arbitrary::Result<Self> {
+		let kind = u.int_in_range(0..=5)?;
+		if kind == 0 {
+			return u
+				.choose(&[Self::INFINITY, Self::NEG_INFINITY, Self::NAN])
+				.cloned();
+		}
+		let (prefix, radix) = [("", 10), ("", 10), ("0x", 16), ("0o", 8), ("0b", 2)][kind - 1];
+		let mut text = String::from(if u.arbitrary()? { "-" } else { "" });
+		text.push_str(prefix);
+		digits(u, &mut text, radix, radix == 10)?;
+		if kind == 1 {
+			let exponent = u.arbitrary::<bool>()?;
+			if !exponent || u.arbitrary()? {
+				text.push('.');
+				digits(u, &mut text, 10, false)?;
+			}
+			if exponent {
+				text.push_str(if u.arbitrary()? { "E+" } else { "E-" });
+				digits(u, &mut text, 10, false)?;
+			}
+		}
+		text.parse().map_err(|()| Error::IncorrectFormat)
+	}
+}
+
+/// Events of a document, without spans.
+fn read(text: &[u8]) -> Result<Vec<Event>, Spanned<ReaderError>> {
+	Reader::new(text)
+		.map(|event| event.map(|(event, _)| event))
+		.collect()
+}
+
+fn write(events: &[Event]) -> String {
+	let mut text = String::new();
+	let mut writer = Writer::new(&mut text);
+	for event in events {
+		writer.push(event).unwrap_or_else(|_| unreachable!());
+	}
+	text
+}
+
+/// Read `text` and write it, then assert that reading and writing that again
+/// gives the same events and text.
+///
+/// Text that isn't a valid document is only read, to check that the reader
+/// doesn't panic.
+///
+/// # Panics
+/// If the round-trip doesn't hold.
+pub fn assert_reread(text: &[u8]) {
+	let Ok(first) = read(text) else {
+		return;
+	};
+	let written = write(&first);
+	let second = read(written.as_bytes()).unwrap_or_else(|(err, span)| {
+		panic!("rewritten text doesn't read at {span:?}: {err}\n{written}")
+	});
+	assert_eq!(
+		first, second,
+		"rewritten text reads differently:\n{written}"
+	);
+	assert_eq!(write(&second), written, "rewritten text writes differently");
+}
+
+/// Assert that turning `document` into events and back gives the same
+/// document and events.
+///
+/// # Panics
+/// If the round-trip doesn't hold.
+pub fn assert_events(document: &Document) {
+	let events = document.iter().collect::<Vec<_>>();
+	let rebuilt = events.iter().cloned().collect::<Document>();
+	assert_eq!(&rebuilt, document, "document changed after events");
+	assert!(rebuilt.iter().eq(events), "events changed after document");
+}
+
+/// Assert that writing `document` and reading it again gives the same
+/// document.
+///
+/// This holds for every generated document, but not all documents, since
+/// writing loses the formatting of numbers built from floats.
+///
+/// # Panics
+/// If the round-trip doesn't hold.
+pub fn assert_rewrite(document: &Document) {
+	let written = document.to_string();
+	let reread = Reader::new(written.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap_or_else(|(err, span)| {
+			panic!("written document doesn't read at {span:?}: {err}\n{written}")
+		});
+	assert_eq!(
+		&reread, document,
+		"written document reads differently:\n{written}"
+	);
+}
+
+/// Assert that writing `number` and parsing it again gives the same number,
+/// that its integer or float value survives converting to a number and back,
+/// and that converting it doesn't panic.
+///
+/// # Panics
+/// If the round-trip doesn't hold.
+pub fn assert_number(number: &Number) {
+	let text = number.to_string();
+	assert_eq!(
+		text.parse().ok().as_ref(),
+		Some(number),
+		"number parses differently: {text}"
+	);
+	number.coerce_to_f32();
+	number.coerce_to_f64();
+	if let Ok(value) = i128::try_from(number) {
+		assert_eq!(
+			i128::try_from(Number::from(value)),
+			Ok(value),
+			"integer value changed: {text}"
+		);
+	}
+	if let Ok(value) = f64::try_from(number) {
+		let again = f64::try_from(Number::from(value));
+		assert!(
+			again.is_ok_and(|again| again.to_bits() == value.to_bits()),
+			"float value changed: {text}"
+		);
+	}
+}
@@ -0,0 +1,371 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Semantic syntax highlighting.
+//!
+//! Unlike the raw [`Lexer`] tokens, this tells node names, type annotations,
+//! and property keys apart from plain values, and finds comments inside
+//! whitespace.
+//!
+//! You probably want to start at [`highlight`].
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::Range;
+
+use crate::dom::Event;
+use crate::lexer::{Lexer, LexerError, Token};
+use crate::reader::{Reader, Spanned};
+
+/// Semantic class of a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Highlight {
+	/// Spaces, newlines, esclines, and byte order marks.
+	Whitespace,
+	/// Line and block comments.
+	Comment,
+	/// Anything commented out by a `/-`, including the `/-`.
+	SlashDash,
+	/// The name of a node.
+	NodeName,
+	/// A type annotation, excluding parentheses.
+	Type,
+	/// The key of a property.
+	PropertyKey,
+	/// A string value.
+	String,
+	/// A numeric value.
+	Number,
+	/// `#true`, `#false`, `#null`, `#inf`, `#-inf`, and `#nan`.
+	Keyword,
+	/// `=`, `;`, parentheses, and braces.
+	Punctuation,
+	/// Invalid text.
+	Error,
+}
+
+impl Highlight {
+	/// Short kebab-case name, used for HTML classes.
+	pub fn name(self) -> &'static str {
+		match self {
+			Highlight::Whitespace => "whitespace",
+			Highlight::Comment => "comment",
+			Highlight::SlashDash => "slash-dash",
+			Highlight::NodeName => "node-name",
+			Highlight::Type => "type",
+			Highlight::PropertyKey => "property-key",
+			Highlight::String => "string",
+			Highlight::Number => "number",
+			Highlight::Keyword => "keyword",
+			Highlight::Punctuation => "punctuation",
+			Highlight::Error => "error",
+		}
+	}
+	/// ANSI SGR parameters for this class, empty for no styling.
+	pub fn ansi_style(self) -> &'static str {
+		match self {
+			Highlight::Whitespace => "",
+			Highlight::Comment => "90",
+			Highlight::SlashDash => "2;90",
+			Highlight::NodeName => "1;34",
+			Highlight::Type => "36",
+			Highlight::PropertyKey => "34",
+			Highlight::String => "32",
+			Highlight::Number => "33",
+			Highlight::Keyword => "35",
+			Highlight::Punctuation => "37",
+			Highlight::Error => "4;31",
+		}
+	}
+}
+
+/// Classify an entire document, returning contiguous spans that cover all of
+/// `text`, with adjacent spans of the same class merged.
+///
+/// Names and types come from the [`Reader`]'s [`parts`](Reader::parts), so
+/// after a syntax error the rest of the text is only classified by its tokens.
+pub fn highlight(text: &[u8]) -> Vec<Spanned<Highlight>> {
+	let mut tokens = Vec::<Spanned<Result<Token, LexerError>>>::new();
+	let mut lexer = Lexer::new(text);
+	loop {
+		let (token, start) = lexer.next_token(true);
+		if let Some((_, prev)) = tokens.last_mut() {
+			prev.end = start;
+		}
+		if matches!(token, Ok(Token::Eof)) {
+			break;
+		}
+		tokens.push((token, start..text.len()));
+	}
+	let mut classes = tokens
+		.iter()
+		.map(|(token, span)| default_class(token, text, span.start))
+		.collect::<Vec<_>>();
+	// spans of every event, in order
+	let mut events = Vec::new();
+	let mut reader = Reader::new(text);
+	let mut error = None;
+	while let Some(event) = reader.next() {
+		let (event, span) = match event {
+			Ok(event) => event,
+			Err((_, span)) => {
+				error = Some(span);
+				break;
+			}
+		};
+		let parts = reader.parts();
+		if let Some(r#type) = &parts.r#type {
+			reclassify(&tokens, &mut classes, r#type, Highlight::Type);
+		}
+		if let Some(name) = &parts.name {
+			let class = match event {
+				Event::Node { .. } => Highlight::NodeName,
+				_ => Highlight::PropertyKey,
+			};
+			reclassify(&tokens, &mut classes, name, class);
+		}
+		events.push(span);
+	}
+	slash_dashes(&tokens, &events, &mut classes);
+	if let Some(error) = error {
+		for ((_, span), class) in tokens.iter().zip(&mut classes) {
+			if span.start < error.end.max(error.start + 1) && error.start < span.end {
+				*class = Highlight::Error;
+			}
+		}
+	}
+	let mut out = Vec::new();
+	for ((_, span), class) in tokens.iter().zip(classes) {
+		if class == Highlight::Whitespace {
+			split_comments(text, span.clone(), &mut out);
+		} else {
+			push(&mut out, class, span.clone());
+		}
+	}
+	out
+}
+
+/// indices of the tokens overlapping `span`
+fn overlapping(tokens: &[Spanned<Result<Token, LexerError>>], span: &Range<usize>) -> Range<usize> {
+	let start = tokens.partition_point(|(_, token)| token.end <= span.start);
+	let end = tokens.partition_point(|(_, token)| token.start < span.end);
+	start..end.max(start)
+}
+
+/// reclassify the strings in `span`, leaving parentheses of types as-is
+fn reclassify(
+	tokens: &[Spanned<Result<Token, LexerError>>],
+	classes: &mut [Highlight],
+	span: &Range<usize>,
+	class: Highlight,
+) {
+	for current in &mut classes[overlapping(tokens, span)] {
+		if *current == Highlight::String {
+			*current = class;
+		}
+	}
+}
+
+/// Slashdashed text has no events, so it runs from a `/-` until the next
+/// event, or the end of the node it comments out.
+fn slash_dashes(
+	tokens: &[Spanned<Result<Token, LexerError>>],
+	events: &[Range<usize>],
+	classes: &mut [Highlight],
+) {
+	let in_event = |span: &Range<usize>| {
+		let index = events.partition_point(|event| event.end <= span.start);
+		events
+			.get(index)
+			.is_some_and(|event| event.start < span.end)
+	};
+	let mut index = 0;
+	while index < tokens.len() {
+		if !matches!(tokens[index].0, Ok(Token::SlashDash)) {
+			index += 1;
+			continue;
+		}
+		let start = index;
+		let mut end = index + 1;
+		let mut depth = 0_usize;
+		// whether anything was commented out yet
+		let mut content = false;
+		index += 1;
+		while let Some((token, span)) = tokens.get(index) {
+			if in_event(span) {
+				break;
+			}
+			match token {
+				Ok(Token::OpenCurly) => depth += 1,
+				Ok(Token::CloseCurly) => match depth.checked_sub(1) {
+					Some(next) => depth = next,
+					// closes the parent
+					None => break,
+				},
+				Ok(Token::Lines | Token::SemiColon) if depth == 0 && content => break,
+				Ok(Token::Spaces | Token::Lines | Token::Bom) => {
+					index += 1;
+					continue;
+				}
+				_ => {}
+			}
+			content = true;
+			index += 1;
+			end = index;
+		}
+		classes[start..end].fill(Highlight::SlashDash);
+	}
+}
+
+fn push(out: &mut Vec<Spanned<Highlight>>, class: Highlight, span: Range<usize>) {
+	match out.last_mut() {
+		Some((last, last_span)) if *last == class && last_span.end == span.start => {
+			last_span.end = span.end;
+		}
+		_ => out.push((class, span)),
+	}
+}
+
+fn default_class(token: &Result<Token, LexerError>, text: &[u8], start: usize) -> Highlight {
+	match token {
+		Ok(Token::Bom | Token::Eof | Token::Lines | Token::Spaces) => Highlight::Whitespace,
+		Ok(Token::String(_) | Token::SkippedString) => Highlight::String,
+		Ok(Token::Number(_) | Token::SkippedNumber) if text.get(start) == Some(&b'#') => {
+			Highlight::Keyword
+		}
+		Ok(Token::Number(_) | Token::SkippedNumber) => Highlight::Number,
+		Ok(Token::Bool(_) | Token::Null) => Highlight::Keyword,
+		Ok(Token::SlashDash) => Highlight::SlashDash,
+		Ok(
+			Token::SemiColon
+			| Token::Equals
+			| Token::OpenParen
+			| Token::CloseParen
+			| Token::OpenCurly
+			| Token::CloseCurly,
+		) => Highlight::Punctuation,
+		Err(_) => Highlight::Error,
+	}
+}
+
+/// split a lexer-validated whitespace span into whitespace & comments
+fn split_comments(text: &[u8], span: Range<usize>, out: &mut Vec<Spanned<Highlight>>) {
+	let end = span.end;
+	let mut i = span.start;
+	while i < end {
+		let rest = &text[i..end];
+		let size = if rest.starts_with(b"//") {
+			rest.iter()
+				.position(|&byte| matches!(byte, b'\n' | b'\x0B' | b'\x0C' | b'\r'))
+				.unwrap_or(rest.len())
+		} else if rest.starts_with(b"/*") {
+			let mut depth = 0_usize;
+			let mut j = 2;
+			loop {
+				match rest.get(j..j + 2) {
+					None => break rest.len(),
+					Some(b"/*") => {
+						depth += 1;
+						j += 2;
+					}
+					Some(b"*/") => {
+						j += 2;
+						match depth.checked_sub(1) {
+							Some(next) => depth = next,
+							None => break j,
+						}
+					}
+					Some(_) => j += 1,
+				}
+			}
+		} else {
+			push(out, Highlight::Whitespace, i..i + 1);
+			i += 1;
+			continue;
+		};
+		push(out, Highlight::Comment, i..i + size);
+		i += size;
+	}
+}
+
+/// Render highlighted text with ANSI escape codes, for terminal output.
+#[derive(Debug, Clone, Copy)]
+pub struct Ansi<'text> {
+	text: &'text [u8],
+	spans: &'text [Spanned<Highlight>],
+}
+
+impl<'text> Ansi<'text> {
+	/// Create a renderer, `spans` should come from [`highlight`].
+	pub fn new(text: &'text [u8], spans: &'text [Spanned<Highlight>]) -> Self {
+		Self { text, spans }
+	}
+}
+
+impl fmt::Display for Ansi<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (class, span) in self.spans {
+			let style = class.ansi_style();
+			if !style.is_empty() {
+				write!(f, "\x1b[{style}m")?;
+			}
+			for chunk in self.text[span.clone()].utf8_chunks() {
+				f.write_str(chunk.valid())?;
+				if !chunk.invalid().is_empty() {
+					f.write_str("\u{FFFD}")?;
+				}
+			}
+			if !style.is_empty() {
+				f.write_str("\x1b[0m")?;
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Render highlighted text as HTML `<span>`s, with `kdl-`-prefixed
+/// [`Highlight::name`] classes.
+///
+/// The output has no container element, wrap it in a `<pre>` to preserve
+/// whitespace.
+#[derive(Debug, Clone, Copy)]
+pub struct Html<'text> {
+	text: &'text [u8],
+	spans: &'text [Spanned<Highlight>],
+}
+
+impl<'text> Html<'text> {
+	/// Create a renderer, `spans` should come from [`highlight`].
+	pub fn new(text: &'text [u8], spans: &'text [Spanned<Highlight>]) -> Self {
+		Self { text, spans }
+	}
+}
+
+impl fmt::Display for Html<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (class, span) in self.spans {
+			let styled = *class != Highlight::Whitespace;
+			if styled {
+				write!(f, "<span class=\"kdl-{}\">", class.name())?;
+			}
+			for chunk in self.text[span.clone()].utf8_chunks() {
+				for ch in chunk.valid().chars() {
+					match ch {
+						'&' => f.write_str("&amp;"),
+						'<' => f.write_str("&lt;"),
+						'>' => f.write_str("&gt;"),
+						'"' => f.write_str("&quot;"),
+						'\'' => f.write_str("&#39;"),
+						ch => fmt::Write::write_char(f, ch),
+					}?;
+				}
+				if !chunk.invalid().is_empty() {
+					f.write_str("\u{FFFD}")?;
+				}
+			}
+			if styled {
+				f.write_str("</span>")?;
+			}
+		}
+		Ok(())
+	}
+}
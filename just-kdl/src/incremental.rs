@@ -0,0 +1,239 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Incremental re-reading for editors.
+//!
+//! An edit re-lexes from the nearest token that can't have seen the edited
+//! text, until the new tokens line up with the previous ones again, so an
+//! unclosed multi-line string or block comment is followed to wherever it
+//! ends. Top-level nodes are then re-read from the nearest node before the
+//! re-lexed text, until they line up in the same way.
+//!
+//! You probably want to start at [`IncrementalDocument`].
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::dom::iter::DocumentBuilder;
+use crate::dom::{Event, Node};
+use crate::lexer::{Lexer, LexerError, Token};
+use crate::reader::{Reader, ReaderError, Spanned};
+
+/// bytes the lexer may read past the end of a token
+const LOOKAHEAD: usize = char::MAX_LEN_UTF8;
+
+/// The top-level nodes replaced by an edit, see
+/// [`IncrementalDocument::edit`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Change {
+	/// Indices of the replaced nodes in the previous node list.
+	pub removed: Range<usize>,
+	/// Indices of their replacements in the new node list.
+	pub inserted: Range<usize>,
+	/// Re-lexed text, in the new text.
+	pub relexed: Range<usize>,
+}
+
+/// A document kept up to date with text edits, without re-reading all of it.
+///
+/// ```
+/// # use just_kdl::incremental::{Change, IncrementalDocument};
+/// let mut document = IncrementalDocument::new("a 1\nb 2\nc 3\n");
+/// let change = document.edit(6..7, "two");
+/// assert_eq!(change.removed, 1..2);
+/// assert_eq!(change.inserted, 1..2);
+/// assert_eq!(document.nodes()[1].0.to_string(), "b two");
+/// ```
+#[derive(Debug, Default)]
+pub struct IncrementalDocument {
+	text: String,
+	tokens: Vec<Spanned<Result<Token, LexerError>>>,
+	nodes: Vec<Spanned<Node>>,
+	error: Option<Spanned<ReaderError>>,
+}
+
+impl IncrementalDocument {
+	/// Read a document from text.
+	pub fn new(text: &str) -> Self {
+		let mut document = Self::default();
+		document.edit(0..0, text);
+		document
+	}
+	/// The current text.
+	pub fn text(&self) -> &str { &self.text }
+	/// Every token of the text, lexed in skip mode (i.e. without string or
+	/// number values).
+	pub fn tokens(&self) -> &[Spanned<Result<Token, LexerError>>] { &self.tokens }
+	/// Top-level nodes, up to the first error.
+	pub fn nodes(&self) -> &[Spanned<Node>] { &self.nodes }
+	/// The first syntax error, if any.
+	pub fn error(&self) -> Option<&Spanned<ReaderError>> { self.error.as_ref() }
+	/// Replace `range` of the text with `replacement`, and report the
+	/// top-level nodes that changed. Nodes outside the change keep their
+	/// values, with spans moved to match the new text.
+	///
+	/// # Panics
+	/// If `range` is out of bounds or not on character boundaries.
+	pub fn edit(&mut self, range: Range<usize>, replacement: &str) -> Change {
+		self.text.replace_range(range.clone(), replacement);
+		let edit_end = range.start + replacement.len();
+		// previous position after the edit → new position
+		let map = |pos: usize| (pos + edit_end).saturating_sub(range.end);
+		let unmap = |pos: usize| pos - edit_end + range.end;
+
+		// re-lex from the last token that can't have seen the edit
+		let first_token = self.tokens[..self
+			.tokens
+			.partition_point(|(_, span)| span.start + LOOKAHEAD <= range.start)]
+			.iter()
+			.rposition(|(token, _)| token.is_ok())
+			.unwrap_or(0);
+		let restart = self
+			.tokens
+			.get(first_token)
+			.map_or(0, |(_, span)| span.start);
+		let old_tokens = &self.tokens[first_token..];
+		let (tokens, token_resync) = lex(&self.text, restart, |pos| {
+			// successful tokens start from a clean lexer state, so the same
+			// start in the same text means the same tokens from then on
+			(pos >= edit_end)
+				.then(|| old_tokens.binary_search_by_key(&unmap(pos), |(_, span)| span.start))?
+				.ok()
+				.filter(|&index| old_tokens[index].0.is_ok())
+				.map(|index| first_token + index)
+		});
+		let relexed =
+			restart..token_resync.map_or(self.text.len(), |index| map(self.tokens[index].1.start));
+		let token_count = tokens.len();
+		let end = token_resync.unwrap_or(self.tokens.len());
+		self.tokens.splice(first_token..end, tokens);
+		for (token, span) in &mut self.tokens[first_token + token_count..] {
+			if let Err(err) = token {
+				err.map_position(map);
+			}
+			*span = map(span.start)..map(span.end);
+		}
+
+		// re-read from the last node that starts before the re-lexed text,
+		// top-level nodes also start from a clean reader state
+		let (first_node, reread) = match self
+			.nodes
+			.partition_point(|(_, span)| span.start <= restart)
+			.checked_sub(1)
+		{
+			Some(index) => (index, self.nodes[index].1.start),
+			None => (0, 0),
+		};
+		let old_nodes = &self.nodes[first_node..];
+		let (mut nodes, node_resync, error) = read(&self.text, reread, |pos| {
+			(pos >= relexed.end)
+				.then(|| old_nodes.binary_search_by_key(&unmap(pos), |(_, span)| span.start))?
+				.ok()
+				.map(|index| first_node + index)
+		});
+		self.error = match node_resync {
+			Some(_) => self.error.take().map(|(mut err, span)| {
+				err.map_position(map);
+				(err, map(span.start)..map(span.end))
+			}),
+			None => error,
+		};
+
+		// only report nodes that actually changed
+		let mut removed = first_node..node_resync.unwrap_or(self.nodes.len());
+		let mut inserted = 0..nodes.len();
+		while !removed.is_empty()
+			&& !inserted.is_empty()
+			&& self.nodes[removed.start] == nodes[inserted.start]
+		{
+			removed.start += 1;
+			inserted.start += 1;
+		}
+		while !removed.is_empty() && !inserted.is_empty() && {
+			let (old_node, old_span) = &self.nodes[removed.end - 1];
+			let (new_node, new_span) = &nodes[inserted.end - 1];
+			old_span.start >= range.end
+				&& (map(old_span.start)..map(old_span.end)) == *new_span
+				&& old_node == new_node
+		} {
+			removed.end -= 1;
+			inserted.end -= 1;
+		}
+		let count = inserted.len();
+		self.nodes.splice(removed.clone(), nodes.drain(inserted));
+		for (_, span) in &mut self.nodes[removed.start + count..] {
+			*span = map(span.start)..map(span.end);
+		}
+		Change {
+			inserted: removed.start..removed.start + count,
+			removed,
+			relexed,
+		}
+	}
+}
+
+/// Lex from `start` until the end of the text or until `resync` finds a
+/// previous token at a successful token start, returning its index.
+fn lex<F: FnMut(usize) -> Option<usize>>(
+	text: &str,
+	start: usize,
+	mut resync: F,
+) -> (Vec<Spanned<Result<Token, LexerError>>>, Option<usize>) {
+	let mut tokens = Vec::<Spanned<Result<Token, LexerError>>>::new();
+	let mut lexer = Lexer::with_position(&text.as_bytes()[start..], start);
+	loop {
+		let (token, pos) = lexer.next_token(true);
+		if let Some((_, prev)) = tokens.last_mut() {
+			prev.end = pos;
+		}
+		if matches!(token, Ok(Token::Eof)) {
+			return (tokens, None);
+		}
+		// errors can happen while finishing the previous token
+		if token.is_ok()
+			&& let Some(index) = resync(pos)
+		{
+			return (tokens, Some(index));
+		}
+		tokens.push((token, pos..text.len()));
+	}
+}
+
+type Read = (
+	Vec<Spanned<Node>>,
+	Option<usize>,
+	Option<Spanned<ReaderError>>,
+);
+
+/// Read top-level nodes from `start` until the end of the text or until
+/// `resync` finds a previous node at a node start, returning its index.
+fn read<F: FnMut(usize) -> Option<usize>>(text: &str, start: usize, mut resync: F) -> Read {
+	let mut reader = Reader::from_lexer(Lexer::with_position(&text.as_bytes()[start..], start));
+	let mut builder = DocumentBuilder::new();
+	let mut nodes = Vec::new();
+	let mut depth = 0_usize;
+	let mut node_start = start;
+	loop {
+		let (event, span) = match reader.next() {
+			None => return (nodes, None, None),
+			Some(Ok(event)) => event,
+			Some(Err(err)) => return (nodes, None, Some(err)),
+		};
+		match event {
+			Event::Node { .. } => {
+				if depth == 0 {
+					if let Some(index) = resync(span.start) {
+						return (nodes, Some(index), None);
+					}
+					node_start = span.start;
+				}
+				depth += 1;
+			}
+			// reader guarantees this won't underflow
+			Event::End => depth -= 1,
+			Event::Entry(_) | Event::Children => {}
+		}
+		if let Some(node) = builder.push(event) {
+			nodes.push((node, node_start..span.end));
+		}
+	}
+}
@@ -0,0 +1,85 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Sharing repeated strings across a parse.
+//!
+//! Strings up to 23 bytes are stored inline in a [`SmolStr`] and never
+//! allocate, longer ones are allocated separately each time they're read. An
+//! [`Interner`] remembers recently read strings so repeats share a single
+//! allocation, which is useful for documents that repeat the same long node
+//! names or property keys many times.
+//!
+//! You probably want to start at [`Interner`].
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use smol_str::SmolStr;
+
+/// Strings this long or shorter are inline, and don't need interning.
+const MAX_INLINE: usize = 23;
+
+/// Bounded cache of shared strings, see
+/// [`Reader::set_interner`](crate::reader::Reader::set_interner).
+///
+/// This is a fixed-size table indexed by the hash of each string, where a
+/// new string replaces whatever was in its slot, so memory use stays bounded
+/// no matter how many distinct strings are read. Keep the interner between
+/// documents to share strings across them.
+///
+/// ```
+/// # use just_kdl::dom::Document;
+/// # use just_kdl::intern::Interner;
+/// # use just_kdl::reader::Reader;
+/// let text = "a-very-long-element-name 1\na-very-long-element-name 2";
+/// let mut reader = Reader::new(text.as_bytes());
+/// reader.set_interner(Some(Interner::default()));
+/// let document = reader.by_ref().collect::<Result<Document, _>>().unwrap();
+/// assert_eq!(document.nodes[0].name.as_ptr(), document.nodes[1].name.as_ptr());
+/// // reuse for the next document
+/// let interner = reader.take_interner();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Interner {
+	slots: Vec<Option<SmolStr>>,
+	max_len: usize,
+}
+
+/// 4096 slots, and strings up to 64 bytes.
+impl Default for Interner {
+	fn default() -> Self { Self::new(4096) }
+}
+
+impl Interner {
+	/// Create a new interner with a number of slots, rounded up to a power of
+	/// two, for strings up to 64 bytes.
+	pub fn new(slots: usize) -> Self {
+		Self {
+			slots: vec![None; slots.max(1).next_power_of_two()],
+			max_len: 64,
+		}
+	}
+	/// Change the longest string to intern, in bytes, longer strings are
+	/// allocated separately as usual.
+	pub fn set_max_len(&mut self, max: usize) { self.max_len = max; }
+	/// Get a shared copy of `text`, remembering it for later.
+	pub fn intern(&mut self, text: &str) -> SmolStr {
+		if text.len() <= MAX_INLINE || text.len() > self.max_len {
+			return SmolStr::new(text);
+		}
+		#[expect(clippy::cast_possible_truncation, reason = "masked to slot count")]
+		let index = (hash(text) as usize) & (self.slots.len() - 1);
+		let slot = &mut self.slots[index];
+		match slot {
+			Some(shared) if shared == text => shared.clone(),
+			_ => slot.insert(SmolStr::new(text)).clone(),
+		}
+	}
+	/// Forget every remembered string.
+	pub fn clear(&mut self) { self.slots.fill(None); }
+}
+
+/// FNV-1a, small and good enough for short strings
+fn hash(text: &str) -> u64 {
+	text.bytes().fold(0xCBF2_9CE4_8422_2325, |hash, byte| {
+		(hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+	})
+}
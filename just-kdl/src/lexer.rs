@@ -1,26 +1,31 @@
 // SPDX-License-Identifier: MIT OR Apache-2.0
 //! Read raw tokens out of a file.
 //!
-//! Possibly useful if you want to implement syntax highlighting.
+//! Possibly useful if you want to implement syntax highlighting, though
+//! [`highlight`] already does that.
 //!
 //! You probably want to start at [`Lexer`].
+//!
+//! [`highlight`]: crate::highlight
 
 use alloc::string::String;
 use alloc::vec::Vec;
+use core::error::Error;
 use core::fmt;
 use core::iter::repeat_n;
 use core::mem::discriminant;
 use core::num::NonZeroUsize;
 use core::ops::Range;
-use std::error::Error;
 
 use displaydoc::Display;
 use smol_str::SmolStr;
 
 use crate::dom::Number;
 use crate::dom::number::NumberBuilder;
+use crate::intern::Interner;
 use crate::ssb2::SmolStrBuilder2;
 
+mod scan;
 #[cfg(test)]
 mod tests;
 
@@ -95,6 +100,7 @@ impl fmt::Display for Token {
 
 /// An error while lexing.
 #[derive(Debug, Display)]
+#[ignore_extra_doc_attributes]
 #[non_exhaustive]
 pub enum LexerError {
 	#[cfg(feature = "std")]
@@ -112,8 +118,8 @@ pub enum LexerError {
 	UnexpectedEof(usize),
 	/// Bad escline body at {0}
 	BadEscline(usize),
-	/// Unexpected plain keyword
-	UnexpectedKeyword,
+	/// Unexpected plain keyword {0:?}
+	UnexpectedKeyword(SmolStr),
 	/// Invalid string escape at {0}
 	InvalidEscape(usize),
 	/// Invalid number value
@@ -122,6 +128,8 @@ pub enum LexerError {
 	BadUnicodeEscape(usize),
 	/// Unexpected newline in single-line string at {0}
 	UnexpectedStringNewline(usize),
+	/// Unclosed raw string at {0}
+	UnclosedRawString(usize),
 	/// Bad raw string start
 	BadRawString,
 	/// Missing newline after multi-line string start
@@ -134,10 +142,82 @@ pub enum LexerError {
 	InvalidOperator,
 	/// Missing expected text
 	MissingText,
+	/// String or identifier longer than {0} bytes
+	///
+	/// Carries the [`Lexer::set_max_string`] limit, the error's span has the
+	/// position.
+	StringLimit(usize),
+	/// Input longer than {0} bytes
+	///
+	/// Carries the [`Lexer::set_max_input`] limit, the error's span has the
+	/// position.
+	InputLimit(usize),
 }
 
 impl Error for LexerError {}
 
+impl LexerError {
+	/// Apply `f` to every position in the error.
+	pub(crate) fn map_position<F: FnOnce(usize) -> usize>(&mut self, f: F) {
+		match self {
+			LexerError::InvalidUtf8(pos)
+			| LexerError::InvalidCharacter(pos)
+			| LexerError::UnexpectedEof(pos)
+			| LexerError::BadEscline(pos)
+			| LexerError::InvalidEscape(pos)
+			| LexerError::BadUnicodeEscape(pos)
+			| LexerError::UnexpectedStringNewline(pos)
+			| LexerError::UnclosedRawString(pos)
+			| LexerError::BadEndString(pos)
+			| LexerError::BadIndent(Some(pos)) => *pos = f(*pos),
+			_ => {}
+		}
+	}
+	/// A suggestion for fixing the error, if there's a likely one.
+	pub fn hint(&self) -> Option<&'static str> {
+		Some(match self {
+			LexerError::UnexpectedKeyword(keyword) => match &**keyword {
+				"true" => "bare `true` is a v1 keyword, use `#true`, or `\"true\"` for a string",
+				"false" => {
+					"bare `false` is a v1 keyword, use `#false`, or `\"false\"` for a string"
+				}
+				"null" => "bare `null` is a v1 keyword, use `#null`, or `\"null\"` for a string",
+				"inf" => "use `#inf` for infinity, or `\"inf\"` for a string",
+				"-inf" => "use `#-inf` for negative infinity, or `\"-inf\"` for a string",
+				"nan" => "use `#nan` for not-a-number, or `\"nan\"` for a string",
+				_ => return None,
+			},
+			LexerError::InvalidNumber => {
+				"identifiers can't start like a number, quote it for a string"
+			}
+			LexerError::InvalidEscape(_) | LexerError::BadUnicodeEscape(_) => {
+				"valid escapes are `\\n \\r \\t \\\\ \\\" \\b \\f \\s \\u{FFFF}`, or use a raw string \
+				 like `#\"C:\\path\"#`"
+			}
+			LexerError::BadEscline(_) => {
+				"only spaces and comments can follow a `\\` line continuation"
+			}
+			LexerError::UnexpectedStringNewline(_) => {
+				"use `\\n` for a newline, or a multi-line `\"\"\"` string"
+			}
+			LexerError::UnclosedRawString(_) | LexerError::BadRawString => {
+				"raw strings end with as many `#`s as they start with, like `##\"text\"##`"
+			}
+			LexerError::MissingStringNewline => {
+				"multi-line strings start with `\"\"\"` and a newline, use `\"` for a single line"
+			}
+			LexerError::BadEndString(_) => {
+				"the closing `\"\"\"` of a multi-line string goes on its own line"
+			}
+			LexerError::BadIndent(_) => {
+				"every line of a multi-line string starts with the indent of the closing `\"\"\"`"
+			}
+			LexerError::InvalidOperator => "`[`, `]`, and `/` are reserved, quote them in strings",
+			_ => return None,
+		})
+	}
+}
+
 /// Don't trust this impl :)
 impl PartialEq for LexerError {
 	fn eq(&self, other: &Self) -> bool { discriminant(self) == discriminant(other) }
@@ -164,7 +244,8 @@ enum NextSkip {
 
 /// Abstract lexer input trait, essentially [`BufRead`] with better ergonomics.
 ///
-/// Notably implemented for <code>&\[[u8]\]</code> and [`ReadInput`].
+/// Notably implemented for <code>&\[[u8]\]</code>, [`ReadInput`],
+/// [`BufReadInput`], [`ChunkInput`], and [`TranscodeInput`].
 ///
 /// [`BufRead`]: std::io::BufRead
 pub trait Input {
@@ -183,18 +264,67 @@ impl Input for &[u8] {
 	fn advance(&mut self, n: usize) { *self = &self[n..]; }
 }
 
-#[cfg(feature = "std")]
 const MAX_PEEK: usize = char::MAX_LEN_UTF8;
 
+/// Small peek buffer for inputs that read into a buffer.
+#[derive(Debug)]
+struct PeekBuffer {
+	buffer: [u8; MAX_PEEK],
+	len: u8,
+}
+
+#[expect(clippy::panic_in_result_fn, reason = "precondition validation")]
+#[expect(
+	clippy::cast_possible_truncation,
+	reason = "start <= request <= MAX_PEEK"
+)]
+impl PeekBuffer {
+	const fn new() -> Self {
+		Self {
+			buffer: [0; MAX_PEEK],
+			len: 0,
+		}
+	}
+	/// `read` fills some of the buffer, returning 0 at end-of-file
+	fn peek<F: FnMut(&mut [u8]) -> LexerResult<usize>>(
+		&mut self,
+		request: usize,
+		mut read: F,
+	) -> LexerResult<&[u8]> {
+		assert!(request <= MAX_PEEK, "target length too long");
+		// manual impl of Read::read_exact, to correctly handle EOF
+		let mut start = usize::from(self.len);
+		while start < request {
+			// allow reading past requested length, that data will be kept after advance
+			match read(&mut self.buffer[start..])? {
+				0 => break,
+				n => start += n,
+			}
+		}
+		self.len = start as u8;
+		Ok(&self.buffer[..start])
+	}
+	fn advance(&mut self, request: usize) {
+		assert!(
+			request <= usize::from(self.len),
+			"target length larger than buffer"
+		);
+		self.buffer =
+			(u32::from_le_bytes(self.buffer).unbounded_shr(8 * request as u32)).to_le_bytes();
+		self.len -= request as u8;
+	}
+}
+
 /// Input from a [`std::io::Read`].
+///
+/// This reads a few bytes at a time, prefer [`BufReadInput`] when the
+/// reader is (or can be) buffered.
 #[cfg(feature = "std")]
 #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 #[derive(Debug)]
 pub struct ReadInput<T> {
 	reader: T,
-	// TODO/perf: able to use BufRead directly somehow?
-	buffer: [u8; MAX_PEEK],
-	buffer_len: u8,
+	buffer: PeekBuffer,
 }
 #[cfg(feature = "std")]
 impl<T> ReadInput<T> {
@@ -202,44 +332,345 @@ impl<T> ReadInput<T> {
 	pub fn new(reader: T) -> Self {
 		Self {
 			reader,
-			buffer: [0; MAX_PEEK],
-			buffer_len: 0,
+			buffer: PeekBuffer::new(),
 		}
 	}
 }
 
 #[cfg(feature = "std")]
 #[expect(clippy::absolute_paths, reason = "feature-gated")]
-#[expect(clippy::panic_in_result_fn, reason = "precondition validation")]
-#[expect(
-	clippy::cast_possible_truncation,
-	reason = "start <= request <= MAX_PEEK"
-)]
 impl<T: std::io::Read> Input for ReadInput<T> {
 	fn peek(&mut self, request: usize) -> LexerResult<&[u8]> {
-		assert!(request <= MAX_PEEK, "target length too long");
-		// manual impl of Read::read_exact, to correctly handle EOF
-		let mut start = usize::from(self.buffer_len);
-		while start < request {
-			// allow reading past requested length, that data will be kept after advance
-			match self.reader.read(&mut self.buffer[start..]) {
-				Ok(0) => break,
-				Ok(n) => start += n,
+		self.buffer.peek(request, |buffer| {
+			loop {
+				match self.reader.read(buffer) {
+					Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+					result => return result.map_err(LexerError::Io),
+				}
+			}
+		})
+	}
+	fn advance(&mut self, request: usize) { self.buffer.advance(request); }
+}
+
+/// Input from a [`std::io::BufRead`], peeking straight into its buffer so
+/// the lexer can scan a whole buffer at a time.
+///
+/// ```
+/// # use std::io::BufReader;
+/// # use just_kdl::dom::Document;
+/// # use just_kdl::lexer::BufReadInput;
+/// # use just_kdl::reader::Reader;
+/// let file = BufReader::new(&b"node \"text\""[..]);
+/// let document = Reader::new(BufReadInput::new(file))
+///     .collect::<Result<Document, _>>()
+///     .unwrap();
+/// assert_eq!(document.to_string(), "node text");
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Debug)]
+pub struct BufReadInput<T> {
+	reader: T,
+	/// characters split between two buffers, already consumed from `reader`
+	split: PeekBuffer,
+}
+#[cfg(feature = "std")]
+impl<T> BufReadInput<T> {
+	/// Create a new instance.
+	pub fn new(reader: T) -> Self {
+		Self {
+			reader,
+			split: PeekBuffer::new(),
+		}
+	}
+}
+
+#[cfg(feature = "std")]
+#[expect(clippy::absolute_paths, reason = "feature-gated")]
+impl<T: std::io::BufRead> BufReadInput<T> {
+	fn fill_buf(reader: &mut T) -> LexerResult<&[u8]> {
+		loop {
+			match reader.fill_buf() {
 				Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+				// can't return the buffer from here yet, rust-lang/rust#54663
 				Err(e) => return Err(LexerError::Io(e)),
+				Ok(_) => break,
 			}
 		}
-		self.buffer_len = start as u8;
-		Ok(&self.buffer[..start])
+		reader.fill_buf().map_err(LexerError::Io)
+	}
+}
+
+#[cfg(feature = "std")]
+#[expect(clippy::absolute_paths, reason = "feature-gated")]
+impl<T: std::io::BufRead> Input for BufReadInput<T> {
+	fn peek(&mut self, request: usize) -> LexerResult<&[u8]> {
+		if self.split.len == 0 {
+			let available = Self::fill_buf(&mut self.reader)?.len();
+			if available >= request || available == 0 {
+				return Self::fill_buf(&mut self.reader);
+			}
+		}
+		// stitch the end of this buffer to the start of the next one
+		self.split.peek(request, |buffer| {
+			let available = Self::fill_buf(&mut self.reader)?;
+			let size = available.len().min(buffer.len());
+			buffer[..size].copy_from_slice(&available[..size]);
+			self.reader.consume(size);
+			Ok(size)
+		})
 	}
 	fn advance(&mut self, request: usize) {
-		assert!(
-			request <= usize::from(self.buffer_len),
-			"target length larger than buffer"
-		);
-		self.buffer =
-			(u32::from_le_bytes(self.buffer).unbounded_shr(8 * request as u32)).to_le_bytes();
-		self.buffer_len -= request as u8;
+		if self.split.len == 0 {
+			self.reader.consume(request);
+		} else {
+			self.split.advance(request);
+		}
+	}
+}
+
+/// Input from a callback that fills a buffer, for byte sources without
+/// [`std::io::Read`], like ring buffers or chunked device reads.
+///
+/// The callback returns how many bytes it wrote, with `0` meaning
+/// end-of-file, and is called with small buffers so it doesn't need to
+/// buffer itself.
+///
+/// ```
+/// # use std::collections::VecDeque;
+/// # use just_kdl::dom::Document;
+/// # use just_kdl::lexer::ChunkInput;
+/// # use just_kdl::reader::Reader;
+/// let mut ring = VecDeque::from(*b"device { baud 9600 }");
+/// let input = ChunkInput::new(|buffer: &mut [u8]| {
+///     let size = buffer.len().min(ring.len());
+///     for (slot, byte) in buffer.iter_mut().zip(ring.drain(..size)) {
+///         *slot = byte;
+///     }
+///     Ok(size)
+/// });
+/// let document = Reader::new(input).collect::<Result<Document, _>>().unwrap();
+/// assert_eq!(document.to_string(), "device {\n    baud 9600\n}");
+/// ```
+pub struct ChunkInput<F> {
+	read: F,
+	buffer: PeekBuffer,
+}
+
+impl<F: FnMut(&mut [u8]) -> LexerResult<usize>> ChunkInput<F> {
+	/// Create a new instance.
+	pub const fn new(read: F) -> Self {
+		Self {
+			read,
+			buffer: PeekBuffer::new(),
+		}
+	}
+}
+
+impl<F> fmt::Debug for ChunkInput<F> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ChunkInput")
+			.field("buffer", &self.buffer)
+			.finish_non_exhaustive()
+	}
+}
+
+impl<F: FnMut(&mut [u8]) -> LexerResult<usize>> Input for ChunkInput<F> {
+	fn peek(&mut self, request: usize) -> LexerResult<&[u8]> {
+		self.buffer.peek(request, &mut self.read)
+	}
+	fn advance(&mut self, request: usize) { self.buffer.advance(request); }
+}
+
+/// Text encoding of a [`TranscodeInput`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Encoding {
+	/// UTF-8, passed through as-is.
+	Utf8,
+	/// UTF-16, little-endian.
+	Utf16Le,
+	/// UTF-16, big-endian.
+	Utf16Be,
+}
+
+/// (UTF-8, source) bytes of the characters in a run of [`TranscodeInput`]
+type Ratio = (usize, usize);
+const ASCII: Ratio = (1, 2);
+
+/// Input in another encoding, transcoded to UTF-8 for the lexer.
+///
+/// The encoding is detected from a byte-order mark, defaulting to UTF-8, or
+/// set explicitly. Invalid text (unpaired surrogates) is reported as
+/// [`LexerError::InvalidUtf8`], and positions reported by the lexer can be
+/// mapped back with [`source_offset`](Self::source_offset).
+///
+/// ```
+/// # use just_kdl::dom::Document;
+/// # use just_kdl::lexer::{Encoding, TranscodeInput};
+/// # use just_kdl::reader::Reader;
+/// let text = "\u{FEFF}node \"✨\" 1"
+///     .encode_utf16()
+///     .flat_map(u16::to_le_bytes)
+///     .collect::<Vec<u8>>();
+/// let mut reader = Reader::new(TranscodeInput::new(&text[..]));
+/// let document = reader.by_ref().collect::<Result<Document, _>>().unwrap();
+/// assert_eq!(document.to_string(), "node ✨ 1");
+/// let input = reader.input();
+/// assert_eq!(input.encoding(), Some(Encoding::Utf16Le));
+/// // the `1` is at byte 14 in UTF-8, and byte 20 in UTF-16
+/// assert_eq!(input.source_offset(14), 20);
+/// ```
+#[derive(Debug)]
+pub struct TranscodeInput<T> {
+	inner: T,
+	encoding: Option<Encoding>,
+	/// transcoded text, starting at `start`
+	buffer: Vec<u8>,
+	start: usize,
+	/// bytes transcoded so far, in UTF-8 and the source encoding
+	decoded: usize,
+	source: usize,
+	/// offsets in UTF-8 and the source encoding where a run of characters
+	/// with the same (UTF-8, source) length ratio starts, after an implicit
+	/// run of ASCII at the start
+	runs: Vec<(usize, usize, Ratio)>,
+}
+
+impl<T: Input> TranscodeInput<T> {
+	/// Create a new instance, detecting the encoding from a byte-order mark.
+	pub const fn new(inner: T) -> Self {
+		Self {
+			inner,
+			encoding: None,
+			buffer: Vec::new(),
+			start: 0,
+			decoded: 0,
+			source: 0,
+			runs: Vec::new(),
+		}
+	}
+	/// Create a new instance with an explicit encoding.
+	pub const fn with_encoding(inner: T, encoding: Encoding) -> Self {
+		let mut this = Self::new(inner);
+		this.encoding = Some(encoding);
+		this
+	}
+	/// The encoding of the input, or `None` if nothing was read yet.
+	pub fn encoding(&self) -> Option<Encoding> { self.encoding }
+	/// Map a position from the lexer (in UTF-8) to the position in the
+	/// source encoding, for positions on character boundaries that were
+	/// already read.
+	pub fn source_offset(&self, offset: usize) -> usize {
+		match self.encoding {
+			None | Some(Encoding::Utf8) => offset,
+			Some(Encoding::Utf16Le | Encoding::Utf16Be) => {
+				let index = self
+					.runs
+					.partition_point(|&(decoded, _, _)| decoded <= offset);
+				let (decoded, source, (utf8, units)) = index
+					.checked_sub(1)
+					.map_or((0, 0, ASCII), |index| self.runs[index]);
+				source + (offset - decoded) / utf8 * units
+			}
+		}
+	}
+	fn detect(&mut self) -> LexerResult<Encoding> {
+		if let Some(encoding) = self.encoding {
+			return Ok(encoding);
+		}
+		let encoding = match self.inner.peek(2)? {
+			[0xFF, 0xFE, ..] => Encoding::Utf16Le,
+			[0xFE, 0xFF, ..] => Encoding::Utf16Be,
+			_ => Encoding::Utf8,
+		};
+		self.encoding = Some(encoding);
+		Ok(encoding)
+	}
+	/// transcode at least `request` bytes, or up to end-of-file
+	fn fill(&mut self, request: usize, big_endian: bool) -> LexerResult<()> {
+		/// stop transcoding a single peek after this many bytes
+		const CHUNK: usize = 1024;
+		self.buffer.drain(..self.start);
+		self.start = 0;
+		let limit = request.max(self.buffer.len() + CHUNK);
+		while self.buffer.len() < request {
+			let units = self.inner.peek(4)?;
+			if units.is_empty() {
+				break;
+			}
+			let mut used = 0;
+			while self.buffer.len() < limit {
+				let rest = &units[used..];
+				let unit = |index: usize| {
+					let bytes = [rest[index], rest[index + 1]];
+					if big_endian {
+						u16::from_be_bytes(bytes)
+					} else {
+						u16::from_le_bytes(bytes)
+					}
+				};
+				let (ch, size) = match rest.len() {
+					// a single byte only at end-of-file
+					1 if used == 0 => (None, 1),
+					0 | 1 => break,
+					_ => match unit(0) {
+						high @ 0xD800..=0xDBFF if rest.len() >= 4 => match unit(2) {
+							low @ 0xDC00..=0xDFFF => {
+								let code = 0x1_0000
+									+ ((u32::from(high) - 0xD800) << 10)
+									+ (u32::from(low) - 0xDC00);
+								(char::from_u32(code), 4)
+							}
+							_ => (None, 2),
+						},
+						// a partial surrogate pair only at end-of-file
+						0xD800..=0xDBFF if used > 0 => break,
+						unit => (char::from_u32(unit.into()), 2),
+					},
+				};
+				let len = if let Some(ch) = ch {
+					let mut encoded = [0; 4];
+					self.buffer
+						.extend_from_slice(ch.encode_utf8(&mut encoded).as_bytes());
+					ch.len_utf8()
+				} else {
+					// never valid in utf-8, so the lexer reports it
+					self.buffer.push(0xFF);
+					1
+				};
+				let ratio = if len == size { (1, 1) } else { (len, size) };
+				if self.runs.last().map_or(ASCII, |&(_, _, last)| last) != ratio {
+					self.runs.push((self.decoded, self.source + used, ratio));
+				}
+				self.decoded += len;
+				used += size;
+			}
+			self.inner.advance(used);
+			self.source += used;
+		}
+		Ok(())
+	}
+}
+
+impl<T: Input> Input for TranscodeInput<T> {
+	fn peek(&mut self, request: usize) -> LexerResult<&[u8]> {
+		let big_endian = match self.detect()? {
+			Encoding::Utf8 => return self.inner.peek(request),
+			Encoding::Utf16Le => false,
+			Encoding::Utf16Be => true,
+		};
+		if self.buffer.len() - self.start < request {
+			self.fill(request, big_endian)?;
+		}
+		Ok(&self.buffer[self.start..])
+	}
+	fn advance(&mut self, request: usize) {
+		match self.encoding {
+			None | Some(Encoding::Utf8) => self.inner.advance(request),
+			Some(Encoding::Utf16Le | Encoding::Utf16Be) => self.start += request,
+		}
 	}
 }
 
@@ -291,19 +722,58 @@ macro_rules! utf8_class {
 	};
 }
 
-fn utf8_len(first: u8) -> usize {
-	// TODO/perf: compare with leading_ones-based solution?
-	const LUT: [u8; 16] = [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 3, 4];
-	LUT[(first >> 4) as usize] as usize
+/// [`Lexer::peek_table`] widths, UTF-8 sequence lengths unless overridden
+const fn widths(overrides: &[(u8, u8)]) -> [u8; 256] {
+	let mut table = [0; 256];
+	let mut byte = 0_u8;
+	loop {
+		table[byte as usize] = scan::class(byte) & scan::LEN;
+		if byte == u8::MAX {
+			break;
+		}
+		byte += 1;
+	}
+	let mut i = 0;
+	while i < overrides.len() {
+		table[overrides[i].0 as usize] = overrides[i].1;
+		i += 1;
+	}
+	table
+}
+
+static UTF8: [u8; 256] = widths(&[]);
+/// `//`, `/*`, `/-`
+static SLASH: [u8; 256] = widths(&[(b'/', 2)]);
+/// `/*`, `*/`
+static COMMENT: [u8; 256] = widths(&[(b'/', 2), (b'*', 2)]);
+/// `"""`, `\r\n`
+static QUOTES: [u8; 256] = widths(&[(b'"', 3), (b'\r', 2)]);
+/// `\r\n`
+static CRLF: [u8; 256] = widths(&[(b'\r', 2)]);
+/// `//`, `/*`, `/-`, `#` keywords
+static TOKEN: [u8; 256] = widths(&[(b'/', 2), (b'#', 3)]);
+static BYTE: [u8; 256] = [1; 256];
+static PAIR: [u8; 256] = [2; 256];
+
+/// end-of-file inside a string
+fn string_eof(hashes: Option<NonZeroUsize>, cursor: usize) -> LexerError {
+	if hashes.is_some() {
+		LexerError::UnclosedRawString(cursor)
+	} else {
+		LexerError::UnexpectedEof(cursor)
+	}
 }
 
+/// text from a [`scan`] run, which is always ASCII
+fn ascii(run: &[u8]) -> &str { str::from_utf8(run).unwrap_or_else(|_| unreachable!()) }
+
 /// Generic "buffer" to abstract over allocation type
 // TODO/perf: try dynamic dispatching this instead of mono
 pub(crate) trait StringOutput {
 	fn so_push_str(&mut self, text: &str);
 	fn so_push_char(&mut self, c: char);
 	fn so_push_close(&mut self, hashes: usize);
-	fn so_finish(self) -> Token;
+	fn so_finish(self, interner: Option<&mut Interner>) -> Token;
 	fn so_finish_num(self, first: u8) -> Option<SmolStr>;
 }
 //impl RawStringOutput for Vec<u8> {}
@@ -314,7 +784,9 @@ impl StringOutput for SmolStrBuilder2 {
 		self.push_str("\"");
 		self.push_repeated(b'#', hashes);
 	}
-	fn so_finish(self) -> Token { Token::String(self.finish()) }
+	fn so_finish(self, interner: Option<&mut Interner>) -> Token {
+		Token::String(self.finish_interned(interner))
+	}
 	fn so_finish_num(mut self, first: u8) -> Option<SmolStr> {
 		self.swap0(first);
 		Some(self.finish())
@@ -324,7 +796,7 @@ impl StringOutput for () {
 	fn so_push_str(&mut self, _text: &str) {}
 	fn so_push_char(&mut self, _c: char) {}
 	fn so_push_close(&mut self, _hashes: usize) {}
-	fn so_finish(self) -> Token { Token::SkippedString }
+	fn so_finish(self, _interner: Option<&mut Interner>) -> Token { Token::SkippedString }
 	fn so_finish_num(self, _first: u8) -> Option<SmolStr> { None }
 }
 
@@ -334,6 +806,11 @@ pub struct Lexer<T> {
 	reader: T,
 	cursor: usize,
 	next_skip: NextSkip,
+	/// cursor limit for the current token
+	limit: usize,
+	max_string: usize,
+	max_input: usize,
+	interner: Option<Interner>,
 }
 
 // TODO/perf: we now only allow utf-8 text again,
@@ -345,16 +822,50 @@ pub struct Lexer<T> {
 #[expect(clippy::unnested_or_patterns, reason = "does not respect utf8_class")]
 impl<T: Input> Lexer<T> {
 	/// Create a new lexer from the input.
-	pub const fn new(input: T) -> Self {
+	pub const fn new(input: T) -> Self { Self::with_position(input, 0) }
+	/// Create a new lexer for input that starts `position` bytes into a
+	/// document, e.g. to resume lexing at a known token boundary. Token and
+	/// error positions include the offset, and a byte-order mark is only
+	/// accepted at position `0`.
+	pub const fn with_position(input: T, position: usize) -> Self {
 		Self {
 			reader: input,
-			cursor: 0,
+			cursor: position,
 			next_skip: NextSkip::None,
+			limit: usize::MAX,
+			max_string: usize::MAX,
+			max_input: usize::MAX,
+			interner: None,
 		}
 	}
+	/// Set the maximum length of a single string or identifier token, in
+	/// input bytes. Longer tokens produce [`LexerError::StringLimit`].
+	pub fn set_max_string(&mut self, max: usize) { self.max_string = max; }
+	/// Set the maximum length of the input, in bytes. Longer inputs produce
+	/// [`LexerError::InputLimit`].
+	pub fn set_max_input(&mut self, max: usize) {
+		self.max_input = max;
+		self.limit = max;
+	}
+	/// Share repeated strings through an interner, default is `None`.
+	pub fn set_interner(&mut self, interner: Option<Interner>) { self.interner = interner; }
+	/// Take the interner back, e.g. to reuse it for another document.
+	pub fn take_interner(&mut self) -> Option<Interner> { self.interner.take() }
 	fn peek(&mut self, n: Range<usize>) -> LexerResult<&[u8]> {
+		if self.cursor > self.limit {
+			return Err(if self.cursor > self.max_input {
+				self.next_skip = NextSkip::IrrecoverableError;
+				LexerError::InputLimit(self.max_input)
+			} else {
+				LexerError::StringLimit(self.max_string)
+			});
+		}
+		// don't let bulk peeks read too far past the limit
+		let end = n
+			.end
+			.min((self.limit - self.cursor).saturating_add(1).max(n.start));
 		match self.reader.peek(n.start) {
-			Ok(result) => Ok(&result[..result.len().min(n.end)]),
+			Ok(result) => Ok(&result[..result.len().min(end)]),
 			Err(err) => {
 				// TODO/perf: i think it's better to have it here (instead of in next_token)
 				self.next_skip = NextSkip::IrrecoverableError;
@@ -362,14 +873,13 @@ impl<T: Input> Lexer<T> {
 			}
 		}
 	}
-	// TODO/perf: replace f with some specific solution like a 256-LUT
-	fn peek_table(&mut self, f: impl FnOnce(u8) -> usize) -> LexerResult<&[u8]> {
+	fn peek_table(&mut self, widths: &[u8; 256]) -> LexerResult<&[u8]> {
 		let &[first] = self.peek(1..1)? else {
 			// TODO/perf: return peek result directly?
 			return Ok(&[]);
 		};
 		// fucked up lifetime things means that i need to do 2 peeks :(
-		let size = f(first);
+		let size = usize::from(widths[usize::from(first)]);
 		self.peek(size..size)
 	}
 	fn advance(&mut self, n: usize) {
@@ -430,10 +940,9 @@ impl<T: Input> Lexer<T> {
 		let mut depth = 0_usize;
 		loop {
 			self.next_skip = NextSkip::RecoverBlockComment(depth);
-			let peek = self.peek_table(|first| match first {
-				b'/' | b'*' => 2,
-				_ => utf8_len(first),
-			})?;
+			let run = scan::plain(self.peek(1..usize::MAX)?, [b'/', b'*']);
+			self.advance(run);
+			let peek = self.peek_table(&COMMENT)?;
 			let size = peek.len();
 			match peek {
 				[] => return Err(LexerError::UnexpectedEof(self.cursor)),
@@ -463,7 +972,9 @@ impl<T: Input> Lexer<T> {
 		self.adv_certain(b"//");
 		self.next_skip = NextSkip::RecoverLineComment;
 		loop {
-			let peek = self.peek_table(utf8_len)?;
+			let run = scan::plain(self.peek(1..usize::MAX)?, [0, 0]);
+			self.advance(run);
+			let peek = self.peek_table(&UTF8)?;
 			let size = peek.len();
 			match peek {
 				[] => break,
@@ -485,10 +996,7 @@ impl<T: Input> Lexer<T> {
 	fn escline(&mut self) -> LexerResult<()> {
 		self.adv_certain(b"\\");
 		loop {
-			let peek = self.peek_table(|first| match first {
-				b'/' => 2,
-				_ => utf8_len(first),
-			})?;
+			let peek = self.peek_table(&SLASH)?;
 			let size = peek.len();
 			match peek {
 				[] => break,
@@ -506,14 +1014,14 @@ impl<T: Input> Lexer<T> {
 	fn string_escape(&mut self) -> LexerResult<Option<char>> {
 		let start_of_escape = self.cursor;
 		self.adv_certain(b"\\");
-		let peek = self.peek_table(utf8_len)?;
+		let peek = self.peek_table(&UTF8)?;
 		let size = peek.len();
 		let ch = match peek {
 			utf8_class!(space) | utf8_class!(line) => {
 				let mut size = size;
 				loop {
 					self.advance(size);
-					let peek_space = self.peek_table(utf8_len)?;
+					let peek_space = self.peek_table(&UTF8)?;
 					size = peek_space.len();
 					if !matches!(peek_space, utf8_class!(space) | utf8_class!(line)) {
 						return Ok(None);
@@ -596,10 +1104,9 @@ impl<T: Input> Lexer<T> {
 	}
 	fn spaces(&mut self) -> LexerResult<()> {
 		loop {
-			let peek = self.peek_table(|first| match first {
-				b'/' => 2,
-				_ => utf8_len(first),
-			})?;
+			let run = scan::class_run(self.peek(1..usize::MAX)?, scan::SPACE);
+			self.advance(run);
+			let peek = self.peek_table(&SLASH)?;
 			let size = peek.len();
 			match peek {
 				[] => break,
@@ -614,10 +1121,9 @@ impl<T: Input> Lexer<T> {
 	}
 	fn lines(&mut self) -> LexerResult<()> {
 		loop {
-			let peek = self.peek_table(|first| match first {
-				b'/' => 2,
-				_ => utf8_len(first),
-			})?;
+			let run = scan::class_run(self.peek(1..usize::MAX)?, scan::SPACE | scan::LINE);
+			self.advance(run);
+			let peek = self.peek_table(&SLASH)?;
 			let size = peek.len();
 			match peek {
 				[] => break,
@@ -655,7 +1161,7 @@ impl<T: Input> Lexer<T> {
 				let size = peek.len();
 				self.advance(size);
 			}
-			if matches!(self.peek_table(utf8_len)?, [] | utf8_class!(not_ident)) {
+			if matches!(self.peek_table(&UTF8)?, [] | utf8_class!(not_ident)) {
 				match builder.finish() {
 					Some(Some(value)) => Ok(Token::Number(value)),
 					Some(None) => Ok(Token::SkippedNumber),
@@ -669,7 +1175,14 @@ impl<T: Input> Lexer<T> {
 			let debug_start = self.cursor;
 			loop {
 				let cursor = self.cursor;
-				let peek = self.peek_table(utf8_len)?;
+				let bulk = self.peek(1..usize::MAX)?;
+				let run = scan::class_run(bulk, scan::IDENT);
+				if run > 0 {
+					text.so_push_str(ascii(&bulk[..run]));
+					self.advance(run);
+					continue;
+				}
+				let peek = self.peek_table(&UTF8)?;
 				let size = peek.len();
 				match peek {
 					[] | utf8_class!(not_ident) => {
@@ -687,7 +1200,7 @@ impl<T: Input> Lexer<T> {
 					}
 				}
 			}
-			Ok(text.so_finish())
+			Ok(text.so_finish(self.interner.as_mut()))
 		}
 	}
 	fn ident(&mut self, skip: bool) -> LexerResult<Token> {
@@ -723,7 +1236,7 @@ impl<T: Input> Lexer<T> {
 					unreachable!()
 				};
 				if matches!(&*text, "true" | "false" | "null" | "inf" | "-inf" | "nan") {
-					Err(LexerError::UnexpectedKeyword)
+					Err(LexerError::UnexpectedKeyword(text))
 				} else if skip {
 					Ok(Token::SkippedString)
 				} else {
@@ -739,12 +1252,17 @@ impl<T: Input> Lexer<T> {
 		hashes: Option<NonZeroUsize>,
 		mut text: impl StringOutput,
 	) -> LexerResult<Token> {
+		let stop = [b'"', if hashes.is_none() { b'\\' } else { b'"' }];
 		'text: loop {
+			let bulk = self.peek(1..usize::MAX)?;
+			let run = scan::plain(bulk, stop);
+			text.so_push_str(ascii(&bulk[..run]));
+			self.advance(run);
 			let cursor = self.cursor;
-			let peek = self.peek_table(utf8_len)?;
+			let peek = self.peek_table(&UTF8)?;
 			let size = peek.len();
 			match peek {
-				[] => return Err(LexerError::UnexpectedEof(cursor)),
+				[] => return Err(string_eof(hashes, cursor)),
 				utf8_class!(invalid) => return Err(LexerError::InvalidCharacter(cursor)),
 				utf8_class!(line) => return Err(LexerError::UnexpectedStringNewline(cursor)),
 				b"\"" => {
@@ -755,7 +1273,7 @@ impl<T: Input> Lexer<T> {
 						let tail = self.peek(1..hashes_left)?;
 						if tail.is_empty() {
 							self.next_skip = NextSkip::None;
-							return Err(LexerError::UnexpectedEof(cursor));
+							return Err(LexerError::UnclosedRawString(cursor));
 						}
 						// TODO/perf: ensure this check is vectorized in some way
 						if !tail.iter().all(|&v| v == b'#') {
@@ -769,7 +1287,7 @@ impl<T: Input> Lexer<T> {
 						self.advance(len);
 					}
 					self.next_skip = NextSkip::None;
-					break Ok(text.so_finish());
+					break Ok(text.so_finish(self.interner.as_mut()));
 				}
 				b"\\" if hashes.is_none() => {
 					if let Some(ch) = self.string_escape()? {
@@ -787,10 +1305,7 @@ impl<T: Input> Lexer<T> {
 	}
 	// return advance distance for newline (if there is any)
 	fn newline_crlf(&mut self) -> LexerResult<Option<NonZeroUsize>> {
-		let peek = self.peek_table(|first| match first {
-			b'\r' => 2,
-			first => utf8_len(first),
-		})?;
+		let peek = self.peek_table(&CRLF)?;
 		Ok(NonZeroUsize::new(match peek {
 			b"\r\n" => 2,
 			[b'\r', ..] => 1,
@@ -804,16 +1319,17 @@ impl<T: Input> Lexer<T> {
 		let mut full_text = String::new();
 		// (line_cursor, line_start, text_start, line_end)
 		let mut lines = Vec::<Option<(usize, usize, usize, usize)>>::new();
+		let stop = [b'"', if hashes.is_none() { b'\\' } else { b'"' }];
 		let tail = 'line: loop {
 			let line_cursor = self.cursor;
 			let line_start = full_text.len();
-			let mut peek_indent = self.peek_table(utf8_len)?;
+			let mut peek_indent = self.peek_table(&UTF8)?;
 			// indent
 			while matches!(peek_indent, utf8_class!(space)) {
 				full_text.push_str(str::from_utf8(peek_indent).unwrap_or_else(|_| unreachable!()));
 				let size = peek_indent.len();
 				self.advance(size);
-				peek_indent = self.peek_table(utf8_len)?;
+				peek_indent = self.peek_table(&UTF8)?;
 			}
 			let text_start = full_text.len();
 			let newline = self.newline_crlf()?;
@@ -823,15 +1339,15 @@ impl<T: Input> Lexer<T> {
 				continue;
 			}
 			'text: loop {
+				let bulk = self.peek(1..usize::MAX)?;
+				let run = scan::plain(bulk, stop);
+				full_text.push_str(ascii(&bulk[..run]));
+				self.advance(run);
 				let cursor = self.cursor;
-				let peek = self.peek_table(|first| match first {
-					b'"' => 3,
-					b'\r' => 2,
-					first => utf8_len(first),
-				})?;
+				let peek = self.peek_table(&QUOTES)?;
 				let size = peek.len();
 				match peek {
-					[] => return Err(LexerError::UnexpectedEof(cursor)),
+					[] => return Err(string_eof(hashes, cursor)),
 					utf8_class!(invalid) => return Err(LexerError::InvalidCharacter(cursor)),
 					[b'\r', ..] | utf8_class!(line) => {
 						lines.push(Some((line_cursor, line_start, text_start, full_text.len())));
@@ -851,7 +1367,7 @@ impl<T: Input> Lexer<T> {
 							let tail = self.peek(1..hashes_left)?;
 							if tail.is_empty() {
 								self.next_skip = NextSkip::None;
-								return Err(LexerError::UnexpectedEof(self.cursor));
+								return Err(LexerError::UnclosedRawString(self.cursor));
 							}
 							// TODO/perf: ensure this check is vectorized in some way
 							if !tail.iter().all(|&v| v == b'#') {
@@ -907,7 +1423,7 @@ impl<T: Input> Lexer<T> {
 				text.push_str(&full_text[line_start + tail_len..line_end]);
 			}
 		}
-		Ok(Token::String(text.finish()))
+		Ok(Token::String(text.finish_interned(self.interner.as_mut())))
 	}
 	// TODO/style: merge inner loop with multiline_string_regular
 	fn multiline_string_skip(&mut self, hashes: Option<NonZeroUsize>) -> LexerResult<Token> {
@@ -916,30 +1432,31 @@ impl<T: Input> Lexer<T> {
 		// it must have no body text. by only reading the indent as the smallest common
 		// one, this validates indents while only allocating one line
 		let mut indent = SmolStrBuilder2::new();
-		let mut peek_indent = self.peek_table(utf8_len)?;
+		let mut peek_indent = self.peek_table(&UTF8)?;
 		// get first line indent
 		while matches!(peek_indent, utf8_class!(space)) {
 			indent.push_str(str::from_utf8(peek_indent).unwrap_or_else(|_| unreachable!()));
 			let size = peek_indent.len();
 			self.advance(size);
-			peek_indent = self.peek_table(utf8_len)?;
+			peek_indent = self.peek_table(&UTF8)?;
 		}
 		let mut next_truncate_length = indent.len();
+		let stop = [b'"', if hashes.is_none() { b'\\' } else { b'"' }];
 		'line: loop {
 			// if there's any leftover indent it's more indented than minimum (or empty)
-			let has_leading_space = matches!(self.peek_table(utf8_len)?, utf8_class!(space));
+			let has_leading_space = matches!(self.peek_table(&UTF8)?, utf8_class!(space));
 			// line body / end of string
 			let mut has_body = false;
 			'text: loop {
+				let bulk = self.peek(1..usize::MAX)?;
+				let run = scan::plain(bulk, stop);
+				has_body |= bulk[..run].iter().any(|&byte| byte != b' ');
+				self.advance(run);
 				let cursor = self.cursor;
-				let peek = self.peek_table(|first| match first {
-					b'"' => 3,
-					b'\r' => 2,
-					first => utf8_len(first),
-				})?;
+				let peek = self.peek_table(&QUOTES)?;
 				let size = peek.len();
 				match peek {
-					[] => return Err(LexerError::UnexpectedEof(cursor)),
+					[] => return Err(string_eof(hashes, cursor)),
 					utf8_class!(invalid) => return Err(LexerError::InvalidCharacter(cursor)),
 					utf8_class!(space) => {
 						// doesn't set body flag
@@ -962,7 +1479,7 @@ impl<T: Input> Lexer<T> {
 							let tail = self.peek(1..hashes_left)?;
 							if tail.is_empty() {
 								self.next_skip = NextSkip::None;
-								return Err(LexerError::UnexpectedEof(self.cursor));
+								return Err(LexerError::UnclosedRawString(self.cursor));
 							}
 							// TODO/perf: ensure this check is vectorized/usize-ized in some way
 							if !tail.iter().all(|&v| v == b'#') {
@@ -1014,7 +1531,8 @@ impl<T: Input> Lexer<T> {
 				matched_bytes += common;
 				let size = peek.len();
 				self.advance(common);
-				if common < size {
+				// an empty peek is end-of-file, reported by the next line
+				if common < size || size == 0 {
 					next_truncate_length = matched_bytes;
 					break;
 				}
@@ -1076,17 +1594,27 @@ impl<T: Input> Lexer<T> {
 			_ => Err(LexerError::BadRawString),
 		}
 	}
+	/// apply the string limit to a token
+	fn limited(&mut self, f: impl FnOnce(&mut Self) -> LexerResult<Token>) -> LexerResult<Token> {
+		self.limit = self
+			.cursor
+			.saturating_add(self.max_string)
+			.min(self.max_input);
+		let result = f(self);
+		self.limit = self.max_input;
+		result
+	}
 	fn advance_err(&mut self, n: usize, err: LexerError) -> LexerError {
 		self.advance(n);
 		err
 	}
 	fn recover_until(
 		&mut self,
-		table: impl Fn(u8) -> usize,
+		widths: &[u8; 256],
 		mut done: impl FnMut(&[u8]) -> bool,
 	) -> LexerResult<()> {
 		loop {
-			match self.peek_table(&table)? {
+			match self.peek_table(widths)? {
 				[] => break,
 				ch => {
 					if done(ch) {
@@ -1106,43 +1634,37 @@ impl<T: Input> Lexer<T> {
 			NextSkip::Spaces => self.spaces()?,
 			NextSkip::Lines => self.lines()?,
 			NextSkip::RecoverLineComment => {
-				self.recover_until(utf8_len, |ch| matches!(ch, utf8_class!(line)))?;
+				self.recover_until(&UTF8, |ch| matches!(ch, utf8_class!(line)))?;
 			}
 			NextSkip::RecoverBlockComment(mut depth) => {
-				self.recover_until(
-					|_| 2,
-					|ch| {
-						if ch == b"*/" {
-							if depth == 0 {
-								true
-							} else {
-								depth -= 1;
-								false
-							}
-						} else if ch == b"/*" {
-							depth += 1;
-							false
+				self.recover_until(&PAIR, |ch| {
+					if ch == b"*/" {
+						if depth == 0 {
+							true
 						} else {
+							depth -= 1;
 							false
 						}
-					},
-				)?;
+					} else if ch == b"/*" {
+						depth += 1;
+						false
+					} else {
+						false
+					}
+				})?;
 			}
 			NextSkip::RecoverString { multiline, hashes } => {
 				let quotes = if multiline { 3_usize } else { 1_usize };
 				let length = quotes + hashes.map_or(0, NonZeroUsize::get);
 				let mut distance = 0;
-				self.recover_until(
-					|_| 1,
-					|ch| {
-						if (ch == b"\"" && distance < quotes) || (ch == b"#" && distance < length) {
-							distance += 1;
-						} else {
-							distance = 0;
-						}
-						distance == length
-					},
-				)?;
+				self.recover_until(&BYTE, |ch| {
+					if (ch == b"\"" && distance < quotes) || (ch == b"#" && distance < length) {
+						distance += 1;
+					} else {
+						distance = 0;
+					}
+					distance == length
+				})?;
 			}
 			NextSkip::IrrecoverableError => return Ok(Token::Eof),
 		}
@@ -1153,11 +1675,7 @@ impl<T: Input> Lexer<T> {
 		// - splitting match by result length (0/1/2/3/4 bytes)
 		// - match keywords with 4 bytes instead of 3 (u32?)
 		// - check on byte-slice matching codegen (in general)
-		let peek = self.peek_table(|first: u8| match first {
-			b'/' => 2,
-			b'#' => 3,
-			_ => utf8_len(first),
-		})?;
+		let peek = self.peek_table(&TOKEN)?;
 		let size = peek.len();
 		Ok(match peek {
 			[] => Token::Eof,
@@ -1182,12 +1700,12 @@ impl<T: Input> Lexer<T> {
 			b"#in" => self.keyword_number(b"#in", b"f", skip, Number::INFINITY)?,
 			b"#-i" => self.keyword_number(b"#-i", b"nf", skip, Number::NEG_INFINITY)?,
 			b"#na" => self.keyword_number(b"#na", b"n", skip, Number::NAN)?,
-			[b'#', ..] | b"\"" => self.string(skip)?,
+			[b'#', ..] | b"\"" => self.limited(|this| this.string(skip))?,
 			b"/-" => self.just(2, Token::SlashDash),
 			b"/*" => self.begin_skip(0, NextSkip::Spaces),
 			b"//" => self.begin_skip(0, NextSkip::Lines),
 			[b'/', ..] => return Err(self.advance_err(1, LexerError::InvalidOperator)),
-			_ => self.ident(skip)?,
+			_ => self.limited(|this| this.ident(skip))?,
 		})
 	}
 	/// Read one token from input, returns value and starting position. This can
@@ -1204,4 +1722,6 @@ impl<T: Input> Lexer<T> {
 	///
 	/// [`next_token`]: Self::next_token
 	pub fn current_position(&mut self) -> usize { self.cursor }
+	/// Get the input.
+	pub fn input(&self) -> &T { &self.reader }
 }
@@ -0,0 +1,166 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+//! Bulk scanning for long runs of plain text.
+//!
+//! String and comment bodies are scanned a word (or vector) at a time for
+//! the first byte that needs the regular per-character path. Identifiers are
+//! usually short, so they use a lookup table a byte at a time instead.
+//!
+//! Building with `--cfg just_kdl_scalar` disables the word-at-a-time paths,
+//! for testing and benchmarking the scalar fallback.
+
+/// [`class`] bits for a UTF-8 sequence length.
+pub(super) const LEN: u8 = 0b111;
+/// [`class`] bit for an ASCII identifier character.
+pub(super) const IDENT: u8 = 1 << 3;
+/// [`class`] bit for ASCII horizontal space.
+pub(super) const SPACE: u8 = 1 << 4;
+/// [`class`] bit for an ASCII newline.
+pub(super) const LINE: u8 = 1 << 5;
+
+/// Class bits of a byte.
+pub(super) const fn class(byte: u8) -> u8 {
+	match byte {
+		b'\t' | b' ' => SPACE | 1,
+		0x0A..=0x0D => LINE | 1,
+		b'/' | b'\\' | b'(' | b')' | b'{' | b'}' | b'[' | b']' | b';' | b'"' | b'#' | b'=' => 1,
+		0x21..=0x7E => IDENT | 1,
+		0xC0..=0xDF => 2,
+		0xE0..=0xEF => 3,
+		0xF0..=0xFF => 4,
+		_ => 1,
+	}
+}
+
+/// Lookup table for the ASCII part of `utf8_class!`, and UTF-8 sequence
+/// lengths.
+static CLASS: [u8; 256] = {
+	let mut table = [0; 256];
+	let mut byte = 0_u8;
+	loop {
+		table[byte as usize] = class(byte);
+		if byte == u8::MAX {
+			break;
+		}
+		byte += 1;
+	}
+	table
+};
+
+/// Length of the leading run of bytes with any of the `class` bits set.
+pub(super) fn class_run(bytes: &[u8], class: u8) -> usize {
+	bytes
+		.iter()
+		.position(|&byte| CLASS[byte as usize] & class == 0)
+		.unwrap_or(bytes.len())
+}
+
+/// Length of the leading run of printable ASCII (`0x20..0x7F`) with neither
+/// of the `stop` bytes.
+pub(super) fn plain(bytes: &[u8], stop: [u8; 2]) -> usize {
+	#[cfg_attr(just_kdl_scalar, expect(unused_mut, reason = "cfg-gated"))]
+	let mut run = 0;
+	#[cfg(not(just_kdl_scalar))]
+	{
+		#[cfg(any(
+			all(target_arch = "x86_64", target_feature = "sse2"),
+			all(target_arch = "aarch64", target_feature = "neon")
+		))]
+		while let Some(chunk) = bytes[run..].first_chunk() {
+			// SAFETY: the target feature is enabled for the whole build
+			let size = unsafe { simd::plain(chunk, stop) };
+			run += size;
+			if size < simd::WIDTH {
+				return run;
+			}
+		}
+		while let Some(&chunk) = bytes[run..].first_chunk() {
+			let size = swar::plain(u64::from_le_bytes(chunk), stop);
+			run += size;
+			if size < swar::WIDTH {
+				return run;
+			}
+		}
+	}
+	run + bytes[run..]
+		.iter()
+		.position(|&byte| !matches!(byte, 0x20..=0x7E) || stop.contains(&byte))
+		.unwrap_or(bytes.len() - run)
+}
+
+#[cfg(not(just_kdl_scalar))]
+mod swar {
+	pub(super) const WIDTH: usize = 8;
+	const HIGH: u64 = splat(0x80);
+	const LOW: u64 = splat(0x7F);
+
+	const fn splat(byte: u8) -> u64 { u64::from_ne_bytes([byte; WIDTH]) }
+	/// high bit set in every byte equal to `byte`, exact (no carries between
+	/// bytes)
+	const fn eq(word: u64, byte: u8) -> u64 {
+		let diff = word ^ splat(byte);
+		!(((diff & LOW) + LOW) | diff)
+	}
+	/// [`plain`](super::plain) for a little-endian word
+	pub(super) const fn plain(word: u64, stop: [u8; 2]) -> usize {
+		// high bit set in bytes below 0x20, when the high bit isn't set already
+		let control = !((word & LOW) + splat(0x80 - 0x20));
+		let special =
+			(word | control | eq(word, 0x7F) | eq(word, stop[0]) | eq(word, stop[1])) & HIGH;
+		(special.trailing_zeros() / 8) as usize
+	}
+}
+
+#[cfg(all(not(just_kdl_scalar), target_arch = "x86_64", target_feature = "sse2"))]
+mod simd {
+	use core::arch::x86_64::{
+		__m128i, _mm_cmpeq_epi8, _mm_cmplt_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_or_si128,
+		_mm_set1_epi8,
+	};
+
+	pub(super) const WIDTH: usize = 16;
+
+	/// [`plain`](super::plain) for one vector
+	#[expect(clippy::cast_possible_wrap, reason = "bytes as lanes")]
+	#[expect(clippy::cast_ptr_alignment, reason = "unaligned load")]
+	#[target_feature(enable = "sse2")]
+	pub(super) fn plain(chunk: &[u8; WIDTH], stop: [u8; 2]) -> usize {
+		// SAFETY: unaligned load of exactly `WIDTH` readable bytes
+		let vector = unsafe { _mm_loadu_si128(chunk.as_ptr().cast::<__m128i>()) };
+		// signed, so bytes from 0x80 are also below 0x20
+		let control = _mm_cmplt_epi8(vector, _mm_set1_epi8(0x20));
+		let delete = _mm_cmpeq_epi8(vector, _mm_set1_epi8(0x7F));
+		let stop0 = _mm_cmpeq_epi8(vector, _mm_set1_epi8(stop[0] as i8));
+		let stop1 = _mm_cmpeq_epi8(vector, _mm_set1_epi8(stop[1] as i8));
+		let special = _mm_or_si128(_mm_or_si128(control, delete), _mm_or_si128(stop0, stop1));
+		// bit per byte, with a stop bit after the last one
+		(_mm_movemask_epi8(special) | 1 << WIDTH).trailing_zeros() as usize
+	}
+}
+
+#[cfg(all(not(just_kdl_scalar), target_arch = "aarch64", target_feature = "neon"))]
+mod simd {
+	use core::arch::aarch64::{
+		vceqq_u8, vcgtq_u8, vcltq_u8, vdupq_n_u8, vget_lane_u64, vld1q_u8, vorrq_u8,
+		vreinterpret_u64_u8, vreinterpretq_u16_u8, vshrn_n_u16,
+	};
+
+	pub(super) const WIDTH: usize = 16;
+
+	/// [`plain`](super::plain) for one vector
+	#[target_feature(enable = "neon")]
+	pub(super) fn plain(chunk: &[u8; WIDTH], stop: [u8; 2]) -> usize {
+		// SAFETY: load of exactly `WIDTH` readable bytes
+		let vector = unsafe { vld1q_u8(chunk.as_ptr()) };
+		let control = vcltq_u8(vector, vdupq_n_u8(0x20));
+		// 0x7F and above
+		let high = vcgtq_u8(vector, vdupq_n_u8(0x7E));
+		let stop0 = vceqq_u8(vector, vdupq_n_u8(stop[0]));
+		let stop1 = vceqq_u8(vector, vdupq_n_u8(stop[1]));
+		let special = vorrq_u8(vorrq_u8(control, high), vorrq_u8(stop0, stop1));
+		// nibble per byte
+		let mask = vget_lane_u64::<0>(vreinterpret_u64_u8(vshrn_n_u16::<4>(vreinterpretq_u16_u8(
+			special,
+		))));
+		(mask.trailing_zeros() / 4) as usize
+	}
+}
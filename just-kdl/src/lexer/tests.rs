@@ -54,6 +54,24 @@ fn lexer_test(
 			tokens_skip,
 			"wrong result (skip read)"
 		);
+		// small buffers split characters between reads
+		for capacity in [1, 3, 64] {
+			use std::io::BufReader;
+
+			use crate::lexer::BufReadInput;
+			let lexer5 = Lexer::new(BufReadInput::new(BufReader::with_capacity(capacity, text)));
+			assert_eq!(
+				lexer_tokens(lexer5, false),
+				tokens,
+				"wrong result (normal buffered {capacity})"
+			);
+			let lexer6 = Lexer::new(BufReadInput::new(BufReader::with_capacity(capacity, text)));
+			assert_eq!(
+				lexer_tokens(lexer6, true),
+				tokens_skip,
+				"wrong result (skip buffered {capacity})"
+			);
+		}
 	}
 }
 
@@ -91,6 +109,38 @@ macro_rules! tests {
 	}
 }
 
+#[test]
+fn scan_plain() {
+	use crate::lexer::scan::plain;
+	// long enough for every chunk size and the scalar tail
+	let mut bytes = [b'a'; 40];
+	for position in 0..bytes.len() {
+		for byte in 0..=u8::MAX {
+			bytes[position] = byte;
+			let expected = if matches!(byte, 0x20..=0x7E) && byte != b'"' && byte != b'\\' {
+				bytes.len()
+			} else {
+				position
+			};
+			assert_eq!(
+				plain(&bytes, [b'"', b'\\']),
+				expected,
+				"byte {byte:#04X} at {position}"
+			);
+		}
+		bytes[position] = b'a';
+	}
+}
+
+#[test]
+fn scan_class() {
+	use crate::lexer::scan::{IDENT, LINE, SPACE, class_run};
+	assert_eq!(class_run(b"ab-c.d\\e", IDENT), 6, "ident");
+	assert_eq!(class_run(b"a\xC2\xA0", IDENT), 1, "non-ASCII");
+	assert_eq!(class_run(b" \t\n ;", SPACE), 2, "spaces");
+	assert_eq!(class_run(b" \t\r\n ;", SPACE | LINE), 5, "lines");
+}
+
 #[expect(non_snake_case, reason = "token replacement")]
 fn String(text: &str) -> Token { Token::String(text.into()) }
 #[expect(non_snake_case, reason = "token replacement")]
@@ -142,13 +192,28 @@ tests! {
 	op_bad: "[" => LexerError::InvalidOperator;
 	bad_slash: "/?" => LexerError::InvalidOperator, String("?");
 	bad_block: "/* /* */ /* */ ** /* *" => Spaces, LexerError::UnexpectedEof(0);
-	banned_idents: "nan" => LexerError::UnexpectedKeyword;
+	banned_idents: "nan" => LexerError::UnexpectedKeyword("nan".into());
 	invalid_utf8_regular: b" \x80" => Spaces, LexerError::InvalidUtf8(0);
 	invalid_utf8_ident: b"a\xF0\x90\x80a" => LexerError::InvalidUtf8(0), LexerError::InvalidUtf8(0), LexerError::InvalidUtf8(0), String("a");
 	invalid_utf8_line_comment: b"// \x80\nrecovery" => Lines, LexerError::InvalidUtf8(0), String("recovery");
 	invalid_utf8_block_comment: b"/* /* \x80 */ */recovery" => Spaces, LexerError::InvalidUtf8(0), String("recovery");
 	invalid_utf8_string: b"\"\x80\" recovery" => LexerError::InvalidUtf8(0), Spaces, String("recovery");
 	escline_bad1: r"a\b" => String("a"), Spaces, LexerError::BadEscline(0), String("b");
+	unclosed_multiline: "\"\"\"\n \n" => LexerError::UnexpectedEof(0);
+	unclosed_raw: "#\"a\"" => LexerError::UnclosedRawString(0);
+	unclosed_raw_hashes: "##\"a\"#" => LexerError::UnclosedRawString(0);
+	unclosed_raw_multiline: "#\"\"\"\n \"\"\"" => LexerError::UnclosedRawString(0);
+	// long enough to take the bulk scanning paths
+	long_string: "\"the quick brown fox jumps over the lazy dog \\\"twice\\\" today\" #\"raw \\ string, also quite long enough\"#"
+	=> String("the quick brown fox jumps over the lazy dog \"twice\" today"), Spaces, String("raw \\ string, also quite long enough");
+	long_multiline: "\"\"\"\n    the quick brown fox jumps over the lazy dog\n      twice, \\\"today\\\"\n    \"\"\""
+	=> String("the quick brown fox jumps over the lazy dog\n  twice, \"today\"");
+	long_comments: "// the quick brown fox jumps over the lazy dog\n/* the quick brown fox /* jumps */ over the lazy dog */a"
+	=> Lines, String("a");
+	long_ident: "the-quick-brown-fox-jumps-over-the-lazy-dog.λ.again;"
+	=> String("the-quick-brown-fox-jumps-over-the-lazy-dog.λ.again"), SemiColon;
+	long_invalid: "\"the quick brown fox jumps over the lazy dog\x7F\""
+	=> LexerError::InvalidCharacter(44);
 
 	// kdl spec string examples
 	spec_singleline1: r#""Hello World""# => String("Hello World");
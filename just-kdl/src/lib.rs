@@ -149,6 +149,21 @@
 //! - *Significantly* fewer temporary allocations
 //! - Fewer final output allocations
 //!
+//! String and comment bodies are scanned a word (or SSE2/NEON vector) at a
+//! time, which lexes text-heavy documents 1.5-2× faster compared
+//! to the scalar fallback (`--cfg just_kdl_scalar`). Median of 5 runs on a
+//! single-core Xeon VM, with the benchmark's generated 88MiB document:
+//! |Scan|Parser|Benchmark|Time|
+//! |:-|:-|:-|:-|
+//! |Scalar|`just-kdl`|text-heavy|0.501s|
+//! |Word/vector|`just-kdl`|text-heavy|0.411s|
+//! |Scalar|`just-kdl` (Lexer)|text-heavy|0.307s|
+//! |Word/vector|`just-kdl` (Lexer)|text-heavy|0.188s|
+//! |Scalar|`just-kdl` (Lexer, skip)|text-heavy|0.224s|
+//! |Word/vector|`just-kdl` (Lexer, skip)|text-heavy|0.118s|
+//!
+//! <small>(Lexer) = tokens only, (skip) = without decoding strings.</small>
+//!
 //! [kdl]: <https://kdl.dev>
 //! [kdl-rs]: https://docs.rs/kdl
 //! [bsrc]: https://github.com/1e1001/rsutil/blob/main/just-kdl/examples/benchmark.rs
@@ -157,7 +172,17 @@ extern crate alloc;
 
 use core::fmt;
 
+pub mod binary;
 pub mod dom;
+#[cfg(feature = "ffi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ffi")))]
+pub mod ffi;
+#[cfg(feature = "arbitrary")]
+#[cfg_attr(docsrs, doc(cfg(feature = "arbitrary")))]
+pub mod fuzz;
+pub mod highlight;
+pub mod incremental;
+pub mod intern;
 pub mod lexer;
 pub mod reader;
 mod ssb2;
@@ -196,6 +221,8 @@ impl fmt::Display for IdentDisplay<'_> {
 			f.write_str("\"")?;
 			for ch in text.chars() {
 				match ch {
+					// not `\0`, which isn't a kdl escape
+					'\0' => f.write_str("\\u{0}"),
 					'\u{8}' => f.write_str("\\b"),
 					'\u{C}' => f.write_str("\\f"),
 					'\'' => f.write_str("'"),
@@ -6,13 +6,15 @@
 // TODO: consider re-merging lexer (or at least using hinted lexing?)
 
 use alloc::vec::Vec;
+use core::error::Error;
 use core::ops::Range;
-use std::error::Error;
 
 use displaydoc::Display;
 use smol_str::SmolStr;
 
-use crate::dom::{Entry, Event, Value};
+use crate::dom::iter::DocumentBuilder;
+use crate::dom::{Entry, Event, Node, Value};
+use crate::intern::Interner;
 use crate::lexer::{Input, Lexer, LexerError, Token};
 
 /// An error while reading
@@ -27,24 +29,158 @@ pub enum ReaderError {
 	ExpectedValue(Token),
 	/// Expected ')', got {0}
 	ExpectedCloseParen(Token),
-	/// Unclosed '{{' before end of file
-	UnclosedOpen,
+	/// Unclosed '{{' from {0} before end of file
+	UnclosedOpen(usize),
 	/// Unmatched '}}'
 	UnmatchedClose,
-	/// Expected '/-' or space before entry
-	ExpectedEntrySpace,
-	/// Expected entry, block, or end of node
-	ExpectedEntry,
-	/// Expected block or end of node
-	ExpectedBlock,
+	/// Expected '/-' or space before entry, got {0}
+	ExpectedEntrySpace(Token),
+	/// Expected entry, block, or end of node, got {0}
+	ExpectedEntry(Token),
+	/// Expected block or end of node, got {0}
+	ExpectedBlock(Token),
+	/// Children blocks nested deeper than {0}
+	DepthLimit(usize),
+	/// More than {0} entries in node
+	EntryLimit(usize),
+	/// More than {0} nodes in document
+	NodeLimit(usize),
 }
 impl Error for ReaderError {}
 
+impl ReaderError {
+	/// Apply `f` to every position in the error.
+	pub(crate) fn map_position<F: FnOnce(usize) -> usize>(&mut self, f: F) {
+		match self {
+			ReaderError::Lexer(err) => err.map_position(f),
+			ReaderError::UnclosedOpen(pos) => *pos = f(*pos),
+			_ => {}
+		}
+	}
+	/// What would have been accepted instead, if anything specific.
+	pub fn expected(&self) -> &'static [Expected] {
+		/// end of node
+		macro_rules! end {
+			($($also:ident),*) => {
+				&[$(Expected::$also,)* Expected::SemiColon, Expected::Newline, Expected::CloseCurly, Expected::Eof]
+			};
+		}
+		match self {
+			ReaderError::Lexer(LexerError::MissingStringNewline) => &[Expected::Newline],
+			ReaderError::Lexer(LexerError::BadRawString) | ReaderError::ExpectedString(_) => {
+				&[Expected::String]
+			}
+			ReaderError::ExpectedValue(_) => &[Expected::Value],
+			ReaderError::ExpectedCloseParen(_) => &[Expected::CloseParen],
+			ReaderError::UnclosedOpen(_) => &[Expected::CloseCurly],
+			ReaderError::ExpectedEntrySpace(_) => end!(Space, SlashDash, OpenCurly),
+			ReaderError::ExpectedEntry(_) => end!(Value, Property, Type, SlashDash, OpenCurly),
+			ReaderError::ExpectedBlock(_) => end!(SlashDash, OpenCurly),
+			_ => &[],
+		}
+	}
+	/// A suggestion for fixing the error, if there's a likely one.
+	pub fn hint(&self) -> Option<&'static str> {
+		Some(match self {
+			ReaderError::Lexer(err) => return err.hint(),
+			ReaderError::ExpectedString(_) => {
+				"names and types are strings, quote them if they look like numbers or keywords"
+			}
+			ReaderError::ExpectedCloseParen(_) => {
+				"type annotations hold a single string, like `(type)`"
+			}
+			ReaderError::UnclosedOpen(_) => "add a `}` to close the block",
+			ReaderError::UnmatchedClose => "this `}` has no matching `{`",
+			ReaderError::ExpectedEntrySpace(_) => "missing space before entry",
+			ReaderError::ExpectedEntry(Token::Equals) => {
+				"property names go directly before `=`, like `key=value`"
+			}
+			ReaderError::ExpectedBlock(_) => {
+				"entries can't follow a children block, move them before the `{`"
+			}
+			_ => return None,
+		})
+	}
+}
+
+/// Something the reader would have accepted, see [`ReaderError::expected`].
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Expected {
+	/// string
+	String,
+	/// value
+	Value,
+	/// property
+	Property,
+	/// type annotation
+	Type,
+	/// ')'
+	CloseParen,
+	/// '{{'
+	OpenCurly,
+	/// '}}'
+	CloseCurly,
+	/// ';'
+	SemiColon,
+	/// newline
+	Newline,
+	/// space
+	Space,
+	/// '/-'
+	SlashDash,
+	/// end of file
+	Eof,
+}
+
 /// Value (event, error) with a span attached
 pub type Spanned<T> = (T, Range<usize>);
 // internal result with error spans
 type ReaderResult<T> = Result<T, Spanned<ReaderError>>;
 
+/// Resource limits for reading untrusted input, see [`Reader::set_limits`].
+///
+/// The default has no limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ReaderLimits {
+	/// Maximum depth of nested children blocks, including commented ones.
+	pub max_depth: usize,
+	/// Maximum length of a single string or identifier, in input bytes.
+	pub max_string: usize,
+	/// Maximum number of entries in a single node.
+	pub max_entries: usize,
+	/// Maximum number of nodes in the document.
+	pub max_nodes: usize,
+	/// Maximum length of the input, in bytes.
+	pub max_input: usize,
+}
+
+impl ReaderLimits {
+	/// No limits.
+	pub const UNLIMITED: Self = Self {
+		max_depth: usize::MAX,
+		max_string: usize::MAX,
+		max_entries: usize::MAX,
+		max_nodes: usize::MAX,
+		max_input: usize::MAX,
+	};
+}
+
+impl Default for ReaderLimits {
+	fn default() -> Self { Self::UNLIMITED }
+}
+
+/// Spans of the parts of an event, see [`Reader::parts`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct EventParts {
+	/// Type annotation, including parentheses.
+	pub r#type: Option<Range<usize>>,
+	/// Node or property name.
+	pub name: Option<Range<usize>>,
+	/// Entry value.
+	pub value: Option<Range<usize>>,
+}
+
 /// Event that might be commented
 #[derive(PartialEq)]
 enum InnerEvent {
@@ -76,9 +212,16 @@ pub struct Reader<T> {
 	// TODO/perf: remove implicit peek/advance pairs in mid-parsing code, looks awful
 	peek_token: Option<Spanned<Token>>,
 	state: State,
-	/// current children block depth, true = sd
+	/// current children block depth, true = sd, and where each one opened
 	// TODO/perf: replace with a bitwise vector of some kind
-	brackets: Vec<bool>,
+	brackets: Vec<(bool, usize)>,
+	limits: ReaderLimits,
+	/// parts of the last event
+	parts: EventParts,
+	/// entries in current node
+	entries: usize,
+	/// nodes in document
+	nodes: usize,
 }
 
 impl<T: Input> Reader<T> {
@@ -91,8 +234,33 @@ impl<T: Input> Reader<T> {
 			peek_token: None,
 			state: State::Begin,
 			brackets: Vec::new(),
+			limits: ReaderLimits::UNLIMITED,
+			parts: EventParts::default(),
+			entries: 0,
+			nodes: 0,
 		}
 	}
+	/// Set resource limits, to reject hostile documents early.
+	pub fn set_limits(&mut self, limits: ReaderLimits) {
+		self.lexer.set_max_string(limits.max_string);
+		self.lexer.set_max_input(limits.max_input);
+		self.limits = limits;
+	}
+	/// Get the current resource limits.
+	pub fn limits(&self) -> ReaderLimits { self.limits }
+	/// Share repeated names and strings through an interner, default is
+	/// `None`, see [`Interner`].
+	pub fn set_interner(&mut self, interner: Option<Interner>) {
+		self.lexer.set_interner(interner);
+	}
+	/// Take the interner back, e.g. to reuse it for another document.
+	pub fn take_interner(&mut self) -> Option<Interner> { self.lexer.take_interner() }
+	/// Spans of the type annotation, name, and value of the last event, the
+	/// event span only covers all of them together.
+	pub fn parts(&self) -> &EventParts { &self.parts }
+	/// Get the input, e.g. to map positions with
+	/// [`TranscodeInput::source_offset`](crate::lexer::TranscodeInput::source_offset).
+	pub fn input(&self) -> &T { self.lexer.input() }
 	fn peek(&mut self, skip: bool) -> ReaderResult<&Spanned<Token>> {
 		// some weird lifetimes here, but it works
 		let mut res = Ok(());
@@ -156,8 +324,8 @@ impl<T: Input> Reader<T> {
 			_ => return Err((ReaderError::ExpectedValue(token.0), token.1)),
 		})
 	}
-	/// String ) Spaces?
-	fn type_body(&mut self, skip: bool) -> ReaderResult<Option<SmolStr>> {
+	/// String ) Spaces?, `open` being the start of the (
+	fn type_body(&mut self, skip: bool, open: usize) -> ReaderResult<Option<SmolStr>> {
 		self.skip_spaces(skip)?;
 		self.peek(skip)?;
 		let text = Self::string(self.advance())?;
@@ -167,14 +335,15 @@ impl<T: Input> Reader<T> {
 		if close.0 != Token::CloseParen {
 			return Err((ReaderError::ExpectedCloseParen(close.0), close.1));
 		}
+		self.parts.r#type = Some(open..close.1.end);
 		self.skip_spaces(skip)?;
 		Ok(text)
 	}
 	/// None = no type or skipped
 	fn maybe_type(&mut self, skip: bool) -> ReaderResult<Option<SmolStr>> {
 		Ok(if self.peek(skip)?.0 == Token::OpenParen {
-			self.advance();
-			self.type_body(skip)?
+			let open = self.advance().1.start;
+			self.type_body(skip, open)?
 		} else {
 			None
 		})
@@ -191,6 +360,7 @@ impl<T: Input> Reader<T> {
 	}
 	#[expect(clippy::too_many_lines, reason = "too lazy to fix this")]
 	fn next_inner_event(&mut self, skip: bool) -> ReaderResult<Spanned<InnerEvent>> {
+		self.parts = EventParts::default();
 		match self.state {
 			State::Begin | State::NextNode => {
 				if self.peek(skip)?.0 == Token::Bom {
@@ -201,15 +371,15 @@ impl<T: Input> Reader<T> {
 				let span = case_token.1.clone();
 				match case_token.0 {
 					Token::Eof => {
-						if !self.brackets.is_empty() {
-							return Err((ReaderError::UnclosedOpen, span));
+						if let Some(&(_, open)) = self.brackets.last() {
+							return Err((ReaderError::UnclosedOpen(open), span));
 						}
 						self.state = State::Done;
 						return Ok((InnerEvent::Done, span));
 					}
 					Token::CloseCurly => {
 						let span = span.clone();
-						let Some(pop) = self.brackets.pop() else {
+						let Some((pop, _)) = self.brackets.pop() else {
 							return Err((ReaderError::UnmatchedClose, span));
 						};
 						self.advance();
@@ -223,6 +393,7 @@ impl<T: Input> Reader<T> {
 				self.peek(skip)?;
 				let name_token = self.advance();
 				let span = span.start..name_token.1.end;
+				self.parts.name = Some(name_token.1.clone());
 				let name = Self::string(name_token)?;
 				self.state = State::NodeEntries(false);
 				Ok((InnerEvent::Node(name.map(|name| (r#type, name))), span))
@@ -265,14 +436,22 @@ impl<T: Input> Reader<T> {
 				let mv_span = mv_token.1.clone();
 				match mv_token.0 {
 					Token::OpenCurly if real_body || sd => {
+						if self.brackets.len() >= self.limits.max_depth {
+							let max = self.limits.max_depth;
+							return Err((ReaderError::DepthLimit(max), start..mv_span.end));
+						}
 						self.advance();
-						self.brackets.push(sd && real_body);
+						self.brackets.push((sd && real_body, mv_span.start));
 						self.state = State::NextNode;
 						Ok((InnerEvent::Children(sd), start..mv_span.end))
 					}
-					_ if !entries => Err((ReaderError::ExpectedBlock, start..mv_span.end)),
+					_ if !entries => {
+						let token = mv_token.0.clone();
+						Err((ReaderError::ExpectedBlock(token), start..mv_span.end))
+					}
 					_ if !sd && !spaces => {
-						Err((ReaderError::ExpectedEntrySpace, start..mv_span.end))
+						let token = mv_token.0.clone();
+						Err((ReaderError::ExpectedEntrySpace(token), start..mv_span.end))
 					}
 					// value or key, unsure
 					Token::String(_) | Token::SkippedString => {
@@ -281,6 +460,7 @@ impl<T: Input> Reader<T> {
 						// skip ever stops after a Entry, change this to a proper difference!
 						let next_spaces = self.skip_spaces(skip)?;
 						if self.peek(skip)?.0 == Token::Equals {
+							self.parts.name = Some(first.1.clone());
 							let name = Self::string(first)?;
 							self.advance();
 							self.skip_spaces(skip_or_sd)?;
@@ -288,6 +468,7 @@ impl<T: Input> Reader<T> {
 							self.peek(skip_or_sd)?;
 							let token = self.advance();
 							let span = start..token.1.end;
+							self.parts.value = Some(token.1.clone());
 							let value = Self::value(skip_or_sd, token)?;
 							Ok((
 								InnerEvent::Entry(
@@ -300,6 +481,7 @@ impl<T: Input> Reader<T> {
 							// consumed spaces that might be needed for the next entry
 							self.state = State::NodeEntries(next_spaces);
 							let span = start..first.1.end;
+							self.parts.value = Some(first.1.clone());
 							let value = Self::value(skip_or_sd, first)?;
 							Ok((
 								InnerEvent::Entry(value.map(|value| (None, None, value))),
@@ -310,6 +492,7 @@ impl<T: Input> Reader<T> {
 					Token::Number(_) | Token::SkippedNumber | Token::Bool(_) | Token::Null => {
 						let token = self.advance();
 						let span = start..token.1.end;
+						self.parts.value = Some(token.1.clone());
 						let value = Self::value(skip_or_sd, token)?;
 						Ok((
 							InnerEvent::Entry(value.map(|value| (None, None, value))),
@@ -318,17 +501,21 @@ impl<T: Input> Reader<T> {
 					}
 					Token::OpenParen => {
 						self.advance();
-						let r#type = self.type_body(skip_or_sd)?;
+						let r#type = self.type_body(skip_or_sd, start)?;
 						self.peek(skip_or_sd)?;
 						let token = self.advance();
 						let span = start..token.1.end;
+						self.parts.value = Some(token.1.clone());
 						let value = Self::value(skip_or_sd, token)?;
 						Ok((
 							InnerEvent::Entry(value.map(|value| (r#type, None, value))),
 							span,
 						))
 					}
-					_ => Err((ReaderError::ExpectedEntry, start..mv_span.end)),
+					_ => {
+						let token = mv_token.0.clone();
+						Err((ReaderError::ExpectedEntry(token), start..mv_span.end))
+					}
 				}
 			}
 			State::Done => {
@@ -362,16 +549,29 @@ impl<T: Input> Reader<T> {
 			let (event, span) = self.next_inner_event(false)?;
 			break (
 				match event {
-					InnerEvent::Node(Some((r#type, name))) => Event::Node { r#type, name },
+					InnerEvent::Node(Some((r#type, name))) => {
+						self.entries = 0;
+						self.nodes += 1;
+						if self.nodes > self.limits.max_nodes {
+							return Err((ReaderError::NodeLimit(self.limits.max_nodes), span));
+						}
+						Event::Node { r#type, name }
+					}
 					InnerEvent::Node(None) => {
 						self.skip_to_end()?;
 						continue;
 					}
-					InnerEvent::Entry(Some((r#type, key, value))) => Event::Entry(Entry {
-						r#type,
-						name: key,
-						value,
-					}),
+					InnerEvent::Entry(Some((r#type, key, value))) => {
+						self.entries += 1;
+						if self.entries > self.limits.max_entries {
+							return Err((ReaderError::EntryLimit(self.limits.max_entries), span));
+						}
+						Event::Entry(Entry {
+							r#type,
+							name: key,
+							value,
+						})
+					}
 					InnerEvent::Entry(None) => continue,
 					InnerEvent::Children(true) => {
 						self.skip_bracketed(&InnerEvent::Children(true), &InnerEvent::End(true))?;
@@ -394,6 +594,20 @@ impl<T: Input> Reader<T> {
 	pub fn skip_to_end(&mut self) -> ReaderResult<()> {
 		self.skip_bracketed(&InnerEvent::Node(None), &InnerEvent::End(false))
 	}
+	/// Read one top-level [`Node`] at a time, without keeping the rest of the
+	/// document in memory.
+	pub fn nodes(self) -> Nodes<T> { self.nodes_at_depth(0) }
+	/// Read every [`Node`] at a specific children-block depth (`0` being
+	/// top-level), discarding the events of their ancestors.
+	pub fn nodes_at_depth(self, depth: usize) -> Nodes<T> {
+		Nodes {
+			reader: self,
+			builder: DocumentBuilder::new(),
+			depth,
+			nest: 0,
+			start: 0,
+		}
+	}
 }
 
 /// Read one event at a time.
@@ -406,3 +620,45 @@ impl<T: Input> Iterator for Reader<T> {
 			.transpose()
 	}
 }
+
+/// Streaming iterator of completed nodes, see [`Reader::nodes`].
+pub struct Nodes<T> {
+	reader: Reader<T>,
+	builder: DocumentBuilder,
+	depth: usize,
+	/// current node depth
+	nest: usize,
+	/// start of the node currently being built
+	start: usize,
+}
+
+impl<T: Input> Iterator for Nodes<T> {
+	type Item = ReaderResult<Spanned<Node>>;
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			let (event, span) = match self.reader.next()? {
+				Ok(event) => event,
+				Err(err) => return Some(Err(err)),
+			};
+			let inside = match event {
+				Event::Node { .. } => {
+					if self.nest == self.depth {
+						self.start = span.start;
+					}
+					self.nest += 1;
+					self.nest > self.depth
+				}
+				Event::End => {
+					let inside = self.nest > self.depth;
+					// reader guarantees this won't underflow
+					self.nest -= 1;
+					inside
+				}
+				Event::Entry(_) | Event::Children => self.nest > self.depth,
+			};
+			if inside && let Some(node) = self.builder.push(event) {
+				return Some(Ok((node, self.start..span.end)));
+			}
+		}
+	}
+}
@@ -9,6 +9,8 @@ use core::iter::repeat_n;
 
 use smol_str::SmolStr;
 
+use crate::intern::Interner;
+
 const MAX_INLINE: usize = 23;
 
 /// do not construct variants manually
@@ -42,6 +44,13 @@ impl SmolStrBuilder2 {
 			SmolStrBuilder2::Heap(text) => SmolStr::from(Arc::from(text.into_boxed_str())),
 		}
 	}
+	/// finish, sharing heap strings through an interner
+	pub fn finish_interned(self, interner: Option<&mut Interner>) -> SmolStr {
+		match (self, interner) {
+			(SmolStrBuilder2::Heap(text), Some(interner)) => interner.intern(&text),
+			(builder, _) => builder.finish(),
+		}
+	}
 	pub fn len(&self) -> usize {
 		match self {
 			SmolStrBuilder2::Inline { size, .. } => *size,
@@ -67,11 +76,8 @@ impl SmolStrBuilder2 {
 		match self {
 			SmolStrBuilder2::Inline { size, buffer } => {
 				// preserves utf-8: writes one codepoint (encoded)
-				debug_assert_eq!(
-					c.encode_utf8(&mut buffer[*size..]).len(),
-					len,
-					"utf8 horrors"
-				);
+				let written = c.encode_utf8(&mut buffer[*size..]).len();
+				debug_assert_eq!(written, len, "utf8 horrors");
 				*size += len;
 			}
 			SmolStrBuilder2::Heap(text) => text.push(c),
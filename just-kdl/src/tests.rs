@@ -4,15 +4,9 @@ mod spec;
 
 /* TODO: fuzzing
 comparing kdl-rs/kdl-js & my kdl
-independent / local tests
-	round-trip ability (bytes → read(1) → write → read(2) leads to 1 == 2)
+independent / local tests (see `crate::fuzz` for the rest)
 	lexer must always produce a complete stream (never panic)
-	reader must always produce a stream or Err (never panic)
-	dom round-trip (events → document → events), except spans
 code coverage things
-Number implementation
-	must never panic!
-	integer / float round-trip (value → Number → Value)
 */
 
 fn test_info(name: &str) -> (&'static str, Option<&'static str>) {
@@ -28,6 +22,1395 @@ fn unnormalized_empty_child_block() {
 	dom::test("document {\n    \n}", Some("document {}\n"), false);
 }
 
+#[test]
+fn streaming_nodes() {
+	use crate::dom::Node;
+	use crate::reader::Reader;
+	let text = "a 1; b { c; d { e } }\nf";
+	let top_nodes = Reader::new(text.as_bytes())
+		.nodes()
+		.map(|node| node.map(|(node, span)| (node.to_string(), span)))
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	assert_eq!(
+		top_nodes,
+		[
+			("a 1".into(), 0..4),
+			("b {\n    c\n    d {\n        e\n    }\n}".into(), 5..21),
+			("f".into(), 22..23),
+		],
+		"top-level nodes"
+	);
+	let nested_nodes = Reader::new(text.as_bytes())
+		.nodes_at_depth(1)
+		.map(|node| node.map(|(node, _)| node))
+		.collect::<Result<Vec<_>, _>>()
+		.unwrap();
+	let nested = Reader::new("d { e }".as_bytes())
+		.collect::<Result<Node, _>>()
+		.unwrap();
+	assert_eq!(nested_nodes, [Node::new("c"), nested], "nested nodes");
+}
+
+#[test]
+fn reader_limits() {
+	use core::ops::Range;
+
+	use crate::lexer::LexerError;
+	use crate::reader::{Reader, ReaderError, ReaderLimits};
+	fn read(edit: impl FnOnce(&mut ReaderLimits)) -> Option<(ReaderError, Range<usize>)> {
+		let text = "a 1 2 3 { b { c \"long string\" } }\nd {}\n/- e { f { g {} } }";
+		let mut limits = ReaderLimits::UNLIMITED;
+		edit(&mut limits);
+		let mut reader = Reader::new(text.as_bytes());
+		reader.set_limits(limits);
+		reader.find_map(Result::err)
+	}
+	assert!(read(|_| {}).is_none(), "no limits");
+	assert!(
+		matches!(read(|l| l.max_depth = 1), Some((ReaderError::DepthLimit(1), span)) if span == (12..13)),
+		"depth limit"
+	);
+	assert!(
+		matches!(read(|l| l.max_entries = 2), Some((ReaderError::EntryLimit(2), span)) if span == (6..7)),
+		"entry limit"
+	);
+	assert!(
+		matches!(read(|l| l.max_nodes = 3), Some((ReaderError::NodeLimit(3), span)) if span == (34..35)),
+		"node limit"
+	);
+	assert!(
+		matches!(
+			read(|l| l.max_string = 8),
+			Some((ReaderError::Lexer(LexerError::StringLimit(8)), _))
+		),
+		"string limit"
+	);
+	assert!(
+		matches!(
+			read(|l| l.max_input = 20),
+			Some((ReaderError::Lexer(LexerError::InputLimit(20)), _))
+		),
+		"input limit"
+	);
+	assert_eq!(
+		LexerError::StringLimit(8).to_string(),
+		"String or identifier longer than 8 bytes",
+		"limit message"
+	);
+	// commented blocks still count towards depth
+	assert!(
+		matches!(
+			read(|l| l.max_depth = 2),
+			Some((ReaderError::DepthLimit(2), _))
+		),
+		"commented depth limit"
+	);
+}
+
+#[test]
+fn error_hints() {
+	use crate::lexer::{LexerError, Token};
+	use crate::reader::{Expected, Reader, ReaderError, Spanned};
+	fn read(text: &str) -> Spanned<ReaderError> {
+		Reader::new(text.as_bytes()).find_map(Result::err).unwrap()
+	}
+	let (keyword, _) = read("a true");
+	assert!(
+		matches!(&keyword, ReaderError::Lexer(LexerError::UnexpectedKeyword(text)) if text == "true"),
+		"keyword text: {keyword:?}"
+	);
+	assert!(
+		keyword.hint().is_some_and(|hint| hint.contains("`#true`")),
+		"keyword hint"
+	);
+	let (raw, _) = read("a ##\"b\"#");
+	assert!(
+		raw.hint().is_some_and(|hint| hint.contains("as many `#`s")),
+		"raw string hint: {raw:?}"
+	);
+	let (space, _) = read("a 1\"b\"");
+	assert!(
+		matches!(&space, ReaderError::ExpectedEntrySpace(Token::String(text)) if text == "b"),
+		"entry space token: {space:?}"
+	);
+	assert_eq!(
+		space.hint(),
+		Some("missing space before entry"),
+		"entry space hint"
+	);
+	assert!(
+		space.expected().contains(&Expected::Space),
+		"entry space expected"
+	);
+	let (entry, _) = read("a =");
+	assert!(
+		matches!(entry, ReaderError::ExpectedEntry(Token::Equals)),
+		"entry token: {entry:?}"
+	);
+	assert!(
+		entry.expected().contains(&Expected::Property),
+		"entry expected"
+	);
+	let (block, _) = read("a {} b");
+	assert!(
+		matches!(block, ReaderError::ExpectedBlock(_)),
+		"block: {block:?}"
+	);
+	assert!(
+		!block.expected().contains(&Expected::Value),
+		"block expected"
+	);
+	// points at the innermost unclosed block
+	let (unclosed, span) = read("a {\n  b {\n    c {}\n");
+	assert!(
+		matches!(unclosed, ReaderError::UnclosedOpen(8)),
+		"unclosed open: {unclosed:?}"
+	);
+	assert_eq!(span, 19..19, "unclosed span");
+	assert_eq!(
+		unclosed.expected(),
+		[Expected::CloseCurly],
+		"unclosed expected"
+	);
+	assert_eq!(
+		unclosed.to_string(),
+		"Unclosed '{' from 8 before end of file",
+		"unclosed message"
+	);
+}
+
+#[test]
+fn canonical() {
+	use core::hash::Hasher;
+
+	use crate::dom::Document;
+	use crate::reader::Reader;
+	/// FNV-1a, stable unlike the std hasher
+	struct Fnv(u64);
+	impl Hasher for Fnv {
+		fn finish(&self) -> u64 { self.0 }
+		fn write(&mut self, bytes: &[u8]) {
+			for &byte in bytes {
+				self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3);
+			}
+		}
+	}
+	fn read(text: &str) -> Document {
+		Reader::new(text.as_bytes())
+			.collect::<Result<_, _>>()
+			.unwrap()
+	}
+	fn hash(document: &Document) -> u64 {
+		let mut hasher = Fnv(0xCBF2_9CE4_8422_2325);
+		document.canonical_hash(&mut hasher);
+		hasher.finish()
+	}
+	let document = read(concat!(
+		"(t)node b=2 a=1 0x10 a=3 z=(u)#null {}\n",
+		"numbers 0b101 0o17 -0 0.0 1.5e3 1.0e-7 123e-8 1e20 1e21 -12.50 #inf\n",
+		"big 0xFFFFFFFFFFFFFFFFFFFFFFFF -0.000123456789e-2\n",
+		"strings \"a\\u{85}b\\\"\\\\\" \"\\t\\u{1}\" #\"raw\"#\n",
+		"parent { child { leaf; empty {} } }\n",
+	));
+	let expected = concat!(
+		"(\"t\")\"node\" 16 \"a\"=3 \"b\"=2 \"z\"=(\"u\")#null\n",
+		"\"numbers\" 5 15 0 0 1500 1E-7 0.00000123 100000000000000000000 1E+21 -12.5 #inf\n",
+		"\"big\" 7.9228162514264337593543950335E+28 -0.00000123456789\n",
+		"\"strings\" \"a\\u{85}b\\\"\\\\\" \"\\t\\u{1}\" \"raw\"\n",
+		"\"parent\" {\n",
+		"    \"child\" {\n",
+		"        \"leaf\"\n",
+		"        \"empty\"\n",
+		"    }\n",
+		"}\n",
+	);
+	let canonical = document.canonical().to_string();
+	assert_eq!(canonical, expected, "canonical form");
+	assert_eq!(
+		read(&canonical).canonical().to_string(),
+		canonical,
+		"canonical form is stable"
+	);
+	assert_eq!(
+		hash(&document),
+		hash(&read(&canonical)),
+		"same hash for same meaning"
+	);
+	assert_ne!(
+		hash(&read("node 1 2")),
+		hash(&read("node 2 1")),
+		"arguments are ordered"
+	);
+	assert_eq!(
+		hash(&Document::new()),
+		0xCBF2_9CE4_8422_2325,
+		"empty document"
+	);
+	// pinned, these only change with a major version
+	assert_eq!(hash(&document), 0x51BC_8EBF_30F0_81B9, "stable hash");
+	assert_eq!(
+		hash(&read("node 1 2")),
+		0x37F4_B083_581A_1D62,
+		"stable small hash"
+	);
+}
+
+#[test]
+fn includes() {
+	use alloc::collections::BTreeMap;
+
+	use crate::dom::include::{FileSpan, IncludeError, Includes};
+	use crate::reader::ReaderError;
+	let files = BTreeMap::from([
+		(
+			"main.kdl",
+			"a\ninclude \"b.kdl\"\nc {\n    include \"d.kdl\"\n}",
+		),
+		("b.kdl", "b1; b2"),
+		("d.kdl", "d { include \"b.kdl\"; }"),
+		("cycle.kdl", "x { include \"cycle2.kdl\"; }"),
+		("cycle2.kdl", "include \"cycle.kdl\""),
+		("bad.kdl", "include \"b.kdl\" extra=1"),
+		("missing.kdl", "\ninclude \"nowhere.kdl\""),
+		("syntax.kdl", "include \"broken.kdl\""),
+		("broken.kdl", "ok\nbroken {"),
+		("custom.kdl", "import \"b.kdl\"; include \"b.kdl\""),
+		("diamond.kdl", "include \"left.kdl\"; include \"right.kdl\""),
+		("left.kdl", "l; include \"shared.kdl\""),
+		("right.kdl", "r; include \"shared.kdl\""),
+		("shared.kdl", "s"),
+	]);
+	let mut includes = Includes::new(files);
+	let included = includes.read("main.kdl").unwrap();
+	assert_eq!(
+		included.document.to_string(),
+		"a\nb1\nb2\nc {\n    d {\n        b1\n        b2\n    }\n}",
+		"document"
+	);
+	assert_eq!(included.files, ["main.kdl", "b.kdl", "d.kdl"], "files");
+	let b2 = &included.spans.nodes[2];
+	assert_eq!(
+		(included.file(&[2]), b2.node.clone()),
+		(Some("b.kdl"), 4..6),
+		"included span"
+	);
+	let c = &included.spans.nodes[3];
+	assert_eq!(included.file(&[3]), Some("main.kdl"), "root span");
+	let d = &c.children.as_ref().unwrap().nodes[0];
+	assert_eq!(
+		(included.file(&[3, 0]), d.node.clone()),
+		(Some("d.kdl"), 0..22),
+		"nested span"
+	);
+	assert_eq!(included.file(&[3, 0, 1]), Some("b.kdl"), "nested include");
+	assert_eq!(included.file(&[4]), None, "missing node");
+	let diamond = includes.read("diamond.kdl").unwrap();
+	assert_eq!(
+		diamond.files,
+		["diamond.kdl", "left.kdl", "shared.kdl", "right.kdl"],
+		"diamond files"
+	);
+	assert_eq!(
+		(diamond.node_files[1].file, diamond.node_files[3].file),
+		(2, 2),
+		"diamond shared index"
+	);
+	let cycle = includes.read("cycle.kdl").unwrap_err();
+	assert!(
+		matches!(&cycle, (IncludeError::Cycle(name), FileSpan { file, span })
+			if name == "cycle.kdl" && file == "cycle2.kdl" && *span == (0..19)),
+		"cycle: {cycle:?}"
+	);
+	let bad = includes.read("bad.kdl").unwrap_err();
+	assert!(
+		matches!(bad.0, IncludeError::BadInclude),
+		"bad include: {bad:?}"
+	);
+	let missing = includes.read("missing.kdl").unwrap_err();
+	assert!(
+		matches!(&missing, (IncludeError::Resolve(path, _), FileSpan { file, span })
+			if path == "nowhere.kdl" && file == "missing.kdl" && *span == (1..22)),
+		"missing: {missing:?}"
+	);
+	let syntax = includes.read("syntax.kdl").unwrap_err();
+	assert!(
+		matches!(&syntax, (IncludeError::Reader(ReaderError::UnclosedOpen(10)), FileSpan { file, .. })
+			if file == "broken.kdl"),
+		"syntax: {syntax:?}"
+	);
+	includes.set_name("import");
+	let custom = includes.read("custom.kdl").unwrap();
+	assert_eq!(
+		custom.document.to_string(),
+		"b1\nb2\ninclude b.kdl",
+		"custom name"
+	);
+}
+
+#[test]
+fn interpolate() {
+	use alloc::collections::BTreeMap;
+
+	use crate::dom::interpolate::{InterpolateError, Interpolator, NodeVariables};
+	use crate::dom::{Document, Value};
+	use crate::reader::Reader;
+	fn read(text: &str) -> Document {
+		Reader::new(text.as_bytes())
+			.collect::<Result<_, _>>()
+			.unwrap()
+	}
+	let variables = BTreeMap::from([
+		("HOST", Value::from("example.com")),
+		("PORT", Value::from(8080)),
+		("DEBUG", Value::from(true)),
+		("EMPTY", Value::from("")),
+		("URL", Value::from("http://${HOST}:${PORT}")),
+		("LOOP", Value::from("${LOOP2}")),
+		("LOOP2", Value::from("x${LOOP}")),
+	]);
+	let interpolate = |text: &str| {
+		let document = read(text);
+		Interpolator::new((NodeVariables(&document), variables.clone())).interpolate(&document)
+	};
+	let document = interpolate(concat!(
+		"defaults tls=#false { port 9000 }\n",
+		"server \"${URL}/api\" port=\"${PORT}\" debug=\"${DEBUG}\" text=\"${PORT}\\n\" {\n",
+		"    fallback \"${MISSING:-${HOST}}\" \"${MISSING:-}\" \"$${HOST} $5\" \"${EMPTY}${PORT}\"\n",
+		"    nodes \"${defaults.port}\" \"${defaults.tls}\" \"${defaults.missing:-none}\"\n",
+		"}\n",
+	))
+	.unwrap();
+	assert_eq!(
+		document.to_string(),
+		concat!(
+			"defaults tls=#false {\n    port 9000\n}\n",
+			"server \"http://example.com:8080/api\" port=8080 debug=#true text=\"8080\\n\" {\n",
+			"    fallback example.com \"\" \"${HOST} $5\" \"8080\"\n",
+			"    nodes 9000 #false none\n",
+			"}",
+		),
+		"interpolated"
+	);
+	let undefined = interpolate("a {\n    b 1 \"${NOPE}\"\n}").unwrap_err();
+	assert_eq!(
+		undefined.to_string(),
+		"Undefined variable \"NOPE\" in a.b[1]",
+		"undefined"
+	);
+	assert!(
+		matches!(&undefined, InterpolateError::Undefined(_, path) if path.nodes == [0, 0] && path.entry == 1),
+		"undefined path: {undefined:?}"
+	);
+	let cycle = interpolate("a key=\"${LOOP}\"").unwrap_err();
+	assert!(
+		matches!(&cycle, InterpolateError::Cycle(name, path) if name == "LOOP" && path.text == "a.key"),
+		"cycle: {cycle:?}"
+	);
+	let unclosed = interpolate("a \"${HOST\"").unwrap_err();
+	assert!(
+		matches!(unclosed, InterpolateError::Unclosed(_)),
+		"unclosed: {unclosed:?}"
+	);
+}
+
+#[test]
+fn binary() {
+	use crate::binary::{BinaryError, Decoder, Encoder};
+	use crate::dom::{Document, Entry, Event, Value};
+	fn decode(bytes: &[u8]) -> Result<Vec<Event>, BinaryError> { Decoder::new(bytes)?.collect() }
+	let node = |name: &str| Event::Node {
+		r#type: None,
+		name: name.into(),
+	};
+	let events = [
+		node("repeated"),
+		Event::Entry(Entry::new_prop("repeated", Value::from(1e100))),
+		Event::Entry(Entry {
+			name: None,
+			r#type: Some("repeated".into()),
+			value: Value::Null,
+		}),
+		Event::Children,
+		node("repeated"),
+		Event::End,
+		Event::End,
+	];
+	let mut encoder = Encoder::new();
+	for event in &events {
+		encoder.push(event);
+	}
+	let bytes = encoder.finish();
+	assert_eq!(decode(&bytes).unwrap(), events, "round-trip");
+	// header, node, new string, prop entry, index, new number, typed null,
+	// index, children, node, index, end, end, done
+	assert_eq!(
+		bytes.len(),
+		5 + 1 + 10 + 1 + 1 + 8 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 1,
+		"strings are interned: {bytes:?}"
+	);
+	assert_eq!(
+		Decoder::new(&Encoder::new().finish())
+			.unwrap()
+			.collect::<Result<Document, _>>()
+			.unwrap(),
+		Document::new(),
+		"empty"
+	);
+}
+
+#[test]
+fn binary_errors() {
+	use crate::binary::{BinaryError, Decoder};
+	use crate::dom::Event;
+	use crate::validator::ValidatorError;
+	fn decode(bytes: &[u8]) -> Result<Vec<Event>, BinaryError> { Decoder::new(bytes)?.collect() }
+	assert!(
+		matches!(decode(b"KDL"), Err(BinaryError::BadHeader)),
+		"short header"
+	);
+	assert!(
+		matches!(decode(b"KDLX\x01\x00"), Err(BinaryError::BadHeader)),
+		"magic"
+	);
+	assert!(
+		matches!(decode(b"KDLB\x02\x00"), Err(BinaryError::Version(2))),
+		"version"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x01\x00\x04ab"),
+			Err(BinaryError::UnexpectedEof(_))
+		),
+		"truncated string"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x01\x00\x01a\x04"),
+			Err(BinaryError::UnexpectedEof(10))
+		),
+		"truncated stream"
+	);
+	assert!(
+		matches!(decode(b"KDLB\x01\x05"), Err(BinaryError::BadTag(5, 5))),
+		"bad tag"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x01\x00\x01a\x34\x04\x00"),
+			Err(BinaryError::BadTag(0x34, 9))
+		),
+		"bad entry kind"
+	);
+	assert!(
+		matches!(decode(b"KDLB\x01\x01\x02"), Err(BinaryError::BadString(6))),
+		"bad string index"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x01\x00\x01\xFF"),
+			Err(BinaryError::BadString(6))
+		),
+		"bad utf-8"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x01\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\xFF\x01"),
+			Err(BinaryError::BadVarint(6))
+		),
+		"varint overflow"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x01\x00\x01a\x24\x00\x02f1\x04\x00"),
+			Err(BinaryError::BadNumber(10))
+		),
+		"bad number"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x04\x00"),
+			Err(BinaryError::Invalid(ValidatorError::TooManyEnd))
+		),
+		"invalid stream"
+	);
+	assert!(
+		matches!(
+			decode(b"KDLB\x01\x01\x00\x01a\x00"),
+			Err(BinaryError::Invalid(ValidatorError::Unclosed))
+		),
+		"unclosed"
+	);
+}
+
+#[test]
+fn event_ref() {
+	use alloc::string::String;
+	use alloc::vec::Vec;
+	use core::ptr::eq as ptr_eq;
+
+	use crate::dom::iter::DocumentBuilder;
+	use crate::dom::{Document, Event, EventRef};
+	use crate::reader::Reader;
+	use crate::validator::Validator;
+	use crate::writer::Writer;
+	let text = "(t)a 1 k=v {\n    b #null\n}\nc";
+	let document = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	let borrowed = document.iter_ref().collect::<Vec<_>>();
+	assert_eq!(
+		borrowed
+			.iter()
+			.copied()
+			.map(Event::from)
+			.collect::<Vec<_>>(),
+		document.iter().collect::<Vec<_>>(),
+		"same events"
+	);
+	let (EventRef::Node { name, .. }, EventRef::Entry(entry)) = (borrowed[0], borrowed[1]) else {
+		panic!("unexpected events {borrowed:?}");
+	};
+	assert!(
+		ptr_eq(name, &raw const document.nodes[0].name),
+		"borrows names"
+	);
+	assert!(
+		ptr_eq(entry, &raw const document.nodes[0].entries[0]),
+		"borrows entries"
+	);
+	let mut output = String::new();
+	let mut writer = Writer::new(&mut output);
+	let mut validator = Validator::new();
+	let mut builder = DocumentBuilder::new();
+	let mut nodes = Vec::new();
+	for &event in &borrowed {
+		writer.push(event).unwrap();
+		validator.push(event).unwrap();
+		nodes.extend(builder.push(event));
+	}
+	validator.done().unwrap();
+	assert_eq!(output, text, "writer");
+	assert_eq!(Document { nodes }, document, "builder");
+	assert_eq!(
+		borrowed.into_iter().collect::<Document>(),
+		document,
+		"collect"
+	);
+	assert_eq!(
+		document.nodes[1].iter_ref().collect::<Vec<_>>(),
+		[
+			EventRef::Node {
+				r#type: None,
+				name: &document.nodes[1].name
+			},
+			EventRef::End
+		],
+		"node"
+	);
+}
+
+#[test]
+fn interner() {
+	use crate::dom::Document;
+	use crate::intern::Interner;
+	use crate::reader::Reader;
+	let read = |text: &str, interner: Option<Interner>| {
+		let mut reader = Reader::new(text.as_bytes());
+		reader.set_interner(interner);
+		let document = reader.by_ref().collect::<Result<Document, _>>().unwrap();
+		(document, reader.take_interner())
+	};
+	let text = "a-very-long-element-name \"a-very-long-element-name\" short=short\na-very-long-element-name \"\"\"\n    a-very-long-element-name\n    \"\"\"";
+	let (plain, _) = read(text, None);
+	let (first, interner) = read(text, Some(Interner::default()));
+	assert_eq!(first, plain, "same document");
+	let name = first.nodes[0].name.as_ptr();
+	assert_ne!(name, plain.nodes[1].name.as_ptr(), "not shared without");
+	let shared = [
+		first.nodes[1].name.as_ptr(),
+		first.nodes[0].entries[0]
+			.value
+			.to_string()
+			.unwrap()
+			.as_ptr(),
+		first.nodes[1].entries[0]
+			.value
+			.to_string()
+			.unwrap()
+			.as_ptr(),
+	];
+	assert_eq!(shared, [name; 3], "shared within document");
+	let (second, _) = read("a-very-long-element-name", interner);
+	assert_eq!(
+		second.nodes[0].name.as_ptr(),
+		name,
+		"shared across documents"
+	);
+	// a single slot, so different strings replace each other
+	let mut small = Interner::new(1);
+	let long = small.intern("a-very-long-element-name");
+	small.intern("another-very-long-element-name");
+	assert_ne!(
+		small.intern("a-very-long-element-name").as_ptr(),
+		long.as_ptr(),
+		"bounded"
+	);
+	small.set_max_len(10);
+	assert_ne!(
+		small.intern("another-very-long-element-name").as_ptr(),
+		small.intern("another-very-long-element-name").as_ptr(),
+		"max length"
+	);
+}
+
+#[cfg(feature = "ffi")]
+mod ffi {
+	use alloc::format;
+	use alloc::string::String;
+	use alloc::vec::Vec;
+	use core::ffi::c_void;
+	use core::mem::MaybeUninit;
+	use core::{ptr, slice, str};
+
+	use crate::ffi::{
+		KdlError, KdlEventKind, KdlReader, KdlStatus, KdlStr, KdlValueKind, kdl_reader_error,
+		kdl_reader_free, kdl_reader_new_buffer, kdl_reader_new_callback, kdl_reader_next,
+	};
+
+	const SOURCE: &str = "(t)node 0x10 k=1.5 \"s\" #true #null {\n    child\n}";
+	const EVENTS: [&str; 10] = [
+		"Node Some(\"t\") Some(\"node\") 0..7",
+		"None None Some(\"0x10\") Some(16) 16.0 false 8..12",
+		"None Some(\"k\") Some(\"1.5\") None 1.5 false 13..18",
+		"None None String Some(\"s\") false 19..22",
+		"None None Bool None true 23..28",
+		"None None Null None false 29..34",
+		"Children None None 35..36",
+		"Node None Some(\"child\") 41..46",
+		"End None None 46..47",
+		"End None None 47..48",
+	];
+
+	fn text(text: KdlStr) -> Option<String> {
+		(!text.ptr.is_null()).then(|| {
+			// SAFETY: strings are valid until the next call
+			let bytes = unsafe { slice::from_raw_parts(text.ptr, text.len) };
+			String::from(str::from_utf8(bytes).unwrap())
+		})
+	}
+	/// every event, and the final status
+	fn events(reader: *mut KdlReader) -> (Vec<String>, KdlStatus) {
+		let mut log = Vec::new();
+		let mut slot = MaybeUninit::uninit();
+		loop {
+			// SAFETY: live reader
+			let status = unsafe { kdl_reader_next(reader, slot.as_mut_ptr()) };
+			if status != KdlStatus::Event {
+				return (log, status);
+			}
+			// SAFETY: written for KdlStatus::Event
+			let event = unsafe { slot.assume_init_ref() };
+			let span = event.span.start..event.span.end;
+			let value = &event.value;
+			log.push(match (event.kind, value.kind) {
+				(KdlEventKind::Entry, KdlValueKind::Number) => format!(
+					"{:?} {:?} {:?} {:?} {:?} {} {span:?}",
+					text(event.r#type),
+					text(event.name),
+					text(value.text),
+					value.is_integer.then_some(value.integer),
+					value.number,
+					value.boolean,
+				),
+				(KdlEventKind::Entry, value_kind) => format!(
+					"{:?} {:?} {value_kind:?} {:?} {} {span:?}",
+					text(event.r#type),
+					text(event.name),
+					text(value.text),
+					value.boolean,
+				),
+				(kind, _) => format!(
+					"{kind:?} {:?} {:?} {span:?}",
+					text(event.r#type),
+					text(event.name)
+				),
+			});
+		}
+	}
+	/// message, hint, and start of the last error
+	fn error(reader: *mut KdlReader) -> Option<(String, Option<String>, usize)> {
+		let mut slot = MaybeUninit::<KdlError>::uninit();
+		// SAFETY: live reader
+		unsafe { kdl_reader_error(reader, slot.as_mut_ptr()) }.then(|| {
+			// SAFETY: written for true
+			let err = unsafe { slot.assume_init() };
+			(text(err.message).unwrap(), text(err.hint), err.span.start)
+		})
+	}
+	/// reads 3 bytes at a time from a `&[u8]`, or fails for null
+	unsafe extern "C" fn read(user: *mut c_void, buffer: *mut u8, len: usize) -> isize {
+		if user.is_null() {
+			return -1;
+		}
+		// SAFETY: user is a `&mut &[u8]`
+		let input = unsafe { &mut *user.cast::<&[u8]>() };
+		let size = len.min(3).min(input.len());
+		// SAFETY: buffer is valid for `len` bytes
+		unsafe { buffer.copy_from_nonoverlapping(input.as_ptr(), size) };
+		*input = &input[size..];
+		isize::try_from(size).unwrap()
+	}
+
+	#[test]
+	fn buffer() {
+		// SAFETY: valid buffer
+		let reader = unsafe { kdl_reader_new_buffer(SOURCE.as_ptr(), SOURCE.len()) };
+		assert_eq!(
+			events(reader),
+			(EVENTS.map(String::from).to_vec(), KdlStatus::Done),
+			"events"
+		);
+		assert_eq!(error(reader), None, "no error");
+		// SAFETY: live reader
+		unsafe { kdl_reader_free(reader) };
+		// SAFETY: null
+		unsafe { kdl_reader_free(ptr::null_mut()) };
+	}
+
+	#[test]
+	fn callback() {
+		let mut input = SOURCE.as_bytes();
+		// SAFETY: `read` is given a `&mut &[u8]`
+		let reader = unsafe { kdl_reader_new_callback(read, (&raw mut input).cast()) };
+		assert_eq!(events(reader).0, EVENTS, "events");
+		// SAFETY: live reader
+		unsafe { kdl_reader_free(reader) };
+		// SAFETY: `read` fails for null
+		let failing = unsafe { kdl_reader_new_callback(read, ptr::null_mut()) };
+		assert_eq!(
+			events(failing),
+			(Vec::new(), KdlStatus::Error),
+			"read error"
+		);
+		assert_eq!(
+			error(failing).unwrap().0,
+			"read callback failed",
+			"read error message"
+		);
+		// SAFETY: live reader
+		unsafe { kdl_reader_free(failing) };
+	}
+
+	#[test]
+	fn errors() {
+		let source = "node {";
+		// SAFETY: valid buffer
+		let reader = unsafe { kdl_reader_new_buffer(source.as_ptr(), source.len()) };
+		assert_eq!(
+			events(reader),
+			(
+				vec![
+					String::from("Node None Some(\"node\") 0..4"),
+					String::from("Children None None 5..6"),
+				],
+				KdlStatus::Error
+			),
+			"syntax error"
+		);
+		assert_eq!(
+			error(reader),
+			Some((
+				String::from("Unclosed '{' from 5 before end of file"),
+				Some(String::from("add a `}` to close the block")),
+				6
+			)),
+			"syntax error message"
+		);
+		assert_eq!(
+			events(reader),
+			(Vec::new(), KdlStatus::Done),
+			"stops after error"
+		);
+		// SAFETY: live reader
+		unsafe { kdl_reader_free(reader) };
+	}
+}
+
+#[cfg(feature = "kdl-interop")]
+#[test]
+fn kdl_interop() {
+	use alloc::string::ToString;
+
+	use kdl::{KdlDocument, KdlIdentifier, KdlValue};
+
+	use crate::dom::{Document, Node, Number, Value};
+	use crate::reader::Reader;
+	let text = "(t)node 1 0x10 -2.5 1e3 #inf #-inf \"text\" #true #null k=(u8)2 {\n    child; (e)empty {}\n}";
+	let document = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	let kdl_document = KdlDocument::parse_v2(text).unwrap();
+	assert_eq!(
+		Document::from(&kdl_document).canonical().to_string(),
+		document.canonical().to_string(),
+		"from kdl"
+	);
+	let converted = KdlDocument::from(&document);
+	let node = &converted.nodes()[0];
+	assert_eq!(
+		node.ty().map(KdlIdentifier::value),
+		Some("t"),
+		"node type annotation"
+	);
+	assert_eq!(
+		node.entries()
+			.iter()
+			.map(|entry| entry.value().clone())
+			.take(6)
+			.collect::<Vec<_>>(),
+		[
+			KdlValue::Integer(1),
+			KdlValue::Integer(16),
+			KdlValue::Float(-2.5),
+			KdlValue::Float(1000.0),
+			KdlValue::Float(f64::INFINITY),
+			KdlValue::Float(f64::NEG_INFINITY),
+		],
+		"numbers"
+	);
+	let prop = node.entry("k").unwrap();
+	assert_eq!(
+		(prop.ty().map(KdlIdentifier::value), prop.value()),
+		(Some("u8"), &KdlValue::Integer(2)),
+		"entry type annotation"
+	);
+	// reparse kdl's output, since it has no normalized representation
+	let reparsed = Reader::new(converted.to_string().as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	assert_eq!(
+		reparsed.canonical().to_string(),
+		document.canonical().to_string(),
+		"to kdl"
+	);
+	assert_eq!(
+		Node::from(kdl::KdlNode::from(Node::new("owned"))),
+		Node::new("owned"),
+		"owned"
+	);
+	assert_eq!(
+		Value::from(KdlValue::from(Value::from(i128::MAX))),
+		Value::from(i128::MAX),
+		"i128"
+	);
+	// too big for kdl to parse, but not for us
+	let huge = "0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"
+		.parse::<Number>()
+		.unwrap();
+	assert_eq!(
+		KdlValue::from(&huge),
+		KdlValue::Float(f64::INFINITY),
+		"past i128"
+	);
+}
+
+#[test]
+fn visit() {
+	use alloc::format;
+	use alloc::string::String;
+	use alloc::vec::Vec;
+
+	use crate::dom::visit::{Edit, Flow, Path, Visitor, VisitorMut};
+	use crate::dom::{Document, Entry, Node};
+	use crate::reader::Reader;
+	struct Log(Vec<String>);
+	impl Visitor for Log {
+		fn enter_node(&mut self, node: &Node, path: &Path) -> Flow {
+			self.0.push(format!("enter {path} {:?}", path.indices()));
+			if node.name() == "skip" {
+				Flow::Skip
+			} else {
+				Flow::Continue
+			}
+		}
+		fn exit_node(&mut self, _: &Node, path: &Path) { self.0.push(format!("exit {path}")); }
+		fn entry(&mut self, entry: &Entry, index: usize, path: &Path) {
+			self.0.push(format!("entry {path} {index} {entry}"));
+		}
+		fn enter_children(&mut self, _: &Document, path: &Path) -> Flow {
+			self.0.push(format!("children {path} {}", path.depth()));
+			Flow::Continue
+		}
+		fn exit_children(&mut self, _: &Document, path: &Path) {
+			self.0.push(format!("end {path}"));
+		}
+	}
+	struct Edits;
+	impl VisitorMut for Edits {
+		fn enter_node(&mut self, node: &mut Node, path: &Path) -> Edit {
+			match node.name() {
+				"remove" => Edit::Remove,
+				"replace" => Edit::Replace(vec![Node::new("x"), Node::new(format!("{path}"))]),
+				_ => {
+					node.set_name(format!("{}{:?}", node.name(), path.indices()));
+					Edit::Continue
+				}
+			}
+		}
+		fn exit_node(&mut self, node: &mut Node, _: &Path) -> Edit {
+			if node
+				.children
+				.as_ref()
+				.is_some_and(|children| children.nodes.is_empty())
+			{
+				Edit::Remove
+			} else {
+				Edit::Continue
+			}
+		}
+		fn entry(&mut self, entry: &mut Entry, _: usize, path: &Path) {
+			entry.value = format!("{path}").into();
+		}
+	}
+	let read = |text: &str| {
+		Reader::new(text.as_bytes())
+			.collect::<Result<Document, _>>()
+			.unwrap()
+	};
+	let mut log = Log(Vec::new());
+	read("a 1 k=2 { b; skip 3 { c } }\nd").visit(&mut log);
+	assert_eq!(
+		log.0,
+		[
+			"enter a [0]",
+			"entry a 0 1",
+			"entry a 1 k=2",
+			"children a 0",
+			"enter a.b [0, 0]",
+			"exit a.b",
+			"enter a.skip [0, 1]",
+			"exit a.skip",
+			"end a",
+			"exit a",
+			"enter d [1]",
+			"exit d",
+		],
+		"visit order"
+	);
+	let mut document = read("remove\na 1 { replace; b 2; remove { c } }\nempty { remove }\nd");
+	document.visit_mut(&mut Edits);
+	assert_eq!(
+		document.to_string(),
+		"\"a[0]\" a {\n    x\n    a.replace\n    \"b[0, 2]\" a.b\n}\n\"d[1]\"",
+		"visit_mut edits"
+	);
+	let mut normalized = read("a k=1 k=2 {}\nb { c {}; d k=1 j=3 k=2 }");
+	normalized.normalize();
+	assert_eq!(
+		normalized.to_string(),
+		"a k=2\nb {\n    c\n    d j=3 k=2\n}",
+		"normalize through visitor"
+	);
+}
+
+#[test]
+fn highlight() {
+	use crate::highlight::Highlight::{
+		Comment, Error, Keyword, NodeName, Number, PropertyKey, Punctuation, SlashDash, String,
+		Type, Whitespace,
+	};
+	use crate::highlight::{Html, highlight};
+	let text = "(t)node \"arg\" key=(u)1 /-skip { a } // hi\n  x #null /* c */ y a=b\nz \"a";
+	let spans = highlight(text.as_bytes())
+		.into_iter()
+		.map(|(class, span)| (class, &text[span]))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		spans,
+		[
+			(Punctuation, "("),
+			(Type, "t"),
+			(Punctuation, ")"),
+			(NodeName, "node"),
+			(Whitespace, " "),
+			(String, "\"arg\""),
+			(Whitespace, " "),
+			(PropertyKey, "key"),
+			(Punctuation, "=("),
+			(Type, "u"),
+			(Punctuation, ")"),
+			(Number, "1"),
+			(Whitespace, " "),
+			(SlashDash, "/-skip"),
+			(Whitespace, " "),
+			(Punctuation, "{"),
+			(Whitespace, " "),
+			(NodeName, "a"),
+			(Whitespace, " "),
+			(Punctuation, "}"),
+			(Whitespace, " "),
+			(Comment, "// hi"),
+			(Whitespace, "\n  "),
+			(NodeName, "x"),
+			(Whitespace, " "),
+			(Keyword, "#null"),
+			(Whitespace, " "),
+			(Comment, "/* c */"),
+			(Whitespace, " "),
+			(String, "y"),
+			(Whitespace, " "),
+			(PropertyKey, "a"),
+			(Punctuation, "="),
+			(String, "b"),
+			(Whitespace, "\n"),
+			(NodeName, "z"),
+			(Whitespace, " "),
+			(Error, "\"a"),
+		],
+		"highlight classes"
+	);
+	let html = b"a<b> /-{ c }";
+	assert_eq!(
+		Html::new(html, &highlight(html)).to_string(),
+		"<span class=\"kdl-node-name\">a&lt;b&gt;</span> <span \
+		 class=\"kdl-slash-dash\">/-{ c }</span>",
+		"html output"
+	);
+}
+
+#[test]
+fn highlight_slash_dash() {
+	use crate::highlight::Highlight::{NodeName, Number, Punctuation, SlashDash, Whitespace};
+	use crate::highlight::highlight;
+	let text = "/-\na 1 {\n  b\n}\nc /-1 2;/-(t)d\ne";
+	let spans = highlight(text.as_bytes())
+		.into_iter()
+		.map(|(class, span)| (class, &text[span]))
+		.collect::<Vec<_>>();
+	assert_eq!(
+		spans,
+		[
+			(SlashDash, "/-\na 1 {\n  b\n}"),
+			(Whitespace, "\n"),
+			(NodeName, "c"),
+			(Whitespace, " "),
+			(SlashDash, "/-1"),
+			(Whitespace, " "),
+			(Number, "2"),
+			(Punctuation, ";"),
+			(SlashDash, "/-(t)d"),
+			(Whitespace, "\n"),
+			(NodeName, "e"),
+		],
+		"slashdashed nodes and entries"
+	);
+}
+
+#[test]
+fn incremental() {
+	use crate::incremental::{Change, IncrementalDocument};
+	let mut document = IncrementalDocument::new("a 1\nb 2\nc 3\nd 4\n");
+	let edits = [
+		// one node
+		(6..7, "two", Change {
+			removed: 1..2,
+			inserted: 1..2,
+			relexed: 2..9,
+		}),
+		// block comment swallows the rest, then gets closed
+		(4..4, "/*", Change {
+			removed: 1..4,
+			inserted: 1..1,
+			relexed: 0..20,
+		}),
+		(12..12, "*/", Change {
+			removed: 1..1,
+			inserted: 1..3,
+			relexed: 3..22,
+		}),
+		// same for a multi-line string
+		(0..0, "x \"\"\"\n", Change {
+			removed: 0..3,
+			inserted: 0..0,
+			relexed: 0..28,
+		}),
+		(10..10, "\"\"\"\n", Change {
+			removed: 0..0,
+			inserted: 0..3,
+			relexed: 1..32,
+		}),
+		// nothing changed
+		(0..1, "x", Change {
+			removed: 1..1,
+			inserted: 1..1,
+			relexed: 0..1,
+		}),
+		// error, then fixed
+		(1..1, "}", Change {
+			removed: 0..3,
+			inserted: 0..1,
+			relexed: 0..2,
+		}),
+		(1..2, "", Change {
+			removed: 0..1,
+			inserted: 0..3,
+			relexed: 0..1,
+		}),
+	];
+	for (range, replacement, change) in edits {
+		let text = document.text().to_owned();
+		assert_eq!(
+			document.edit(range.clone(), replacement),
+			change,
+			"change of {range:?} to {replacement:?} in {text:?}"
+		);
+		let fresh = IncrementalDocument::new(document.text());
+		assert_eq!(
+			format!("{document:?}"),
+			format!("{fresh:?}"),
+			"incremental state of {:?}",
+			document.text()
+		);
+	}
+}
+
+#[test]
+#[cfg(feature = "std")]
+fn io_writer() {
+	use std::io::{self, ErrorKind, Write};
+
+	use crate::dom::Document;
+	use crate::reader::Reader;
+	use crate::writer::WriterError;
+	struct Full;
+	impl Write for Full {
+		fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+			Err(io::Error::new(ErrorKind::StorageFull, "disk full"))
+		}
+		fn flush(&mut self) -> io::Result<()> { Ok(()) }
+	}
+	let document = Reader::new("a 1; b { c \"d\" }".as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	let mut output = Vec::new();
+	document.write_to(&mut output).unwrap();
+	assert_eq!(output, document.to_string().as_bytes(), "same as display");
+	output.clear();
+	document.nodes[1].write_to(&mut output).unwrap();
+	assert_eq!(output, document.nodes[1].to_string().as_bytes(), "node");
+	assert!(
+		matches!(
+			document.write_to(Full),
+			Err(WriterError::Io(err)) if err.kind() == ErrorKind::StorageFull
+		),
+		"keeps io error"
+	);
+}
+
+#[test]
+fn checked_writer() {
+	use crate::dom::{Entry, Event, Value};
+	use crate::validator::ValidatorError;
+	use crate::writer::{CheckedWriter, Writer, WriterError};
+	let node = Event::Node {
+		r#type: None,
+		name: "a".into(),
+	};
+	let entry = Event::Entry(Entry::new_value(Value::Null));
+	let mut writer = CheckedWriter::new(String::new());
+	assert!(
+		matches!(
+			writer.push(&Event::End),
+			Err(WriterError::Invalid(ValidatorError::TooManyEnd))
+		),
+		"end without node"
+	);
+	for event in [&node, &entry, &Event::Children] {
+		writer.push(event).unwrap();
+	}
+	assert!(
+		matches!(
+			writer.push(&entry),
+			Err(WriterError::Invalid(ValidatorError::Expected(..)))
+		),
+		"entry after children"
+	);
+	writer.push(&node).unwrap();
+	writer.push(&Event::End).unwrap();
+	assert!(
+		matches!(
+			writer.finish(),
+			Err(WriterError::Invalid(ValidatorError::Unclosed))
+		),
+		"unclosed node"
+	);
+	let mut checked = CheckedWriter::new(String::new());
+	let mut unchecked = String::new();
+	let mut plain = Writer::new(&mut unchecked);
+	for event in [
+		&node,
+		&entry,
+		&Event::Children,
+		&node,
+		&Event::End,
+		&Event::End,
+	] {
+		checked.push(event).unwrap();
+		plain.push(event).unwrap();
+	}
+	assert_eq!(checked.finish().unwrap(), unchecked, "same output");
+	// unchecked writer doesn't panic
+	let mut garbage = Writer::new(String::new());
+	for event in [&node, &Event::End, &Event::End] {
+		garbage.push(event).unwrap();
+	}
+	#[cfg(feature = "std")]
+	{
+		use crate::writer::IoWriter;
+		let mut io = IoWriter::new(Vec::new());
+		io.set_checked(true);
+		io.push(&node).unwrap();
+		assert!(
+			matches!(
+				io.finish(),
+				Err(WriterError::Invalid(ValidatorError::Unclosed))
+			),
+			"checked io writer"
+		);
+	}
+}
+
+#[test]
+fn wrapping_writer() {
+	use alloc::string::String;
+
+	use crate::dom::Document;
+	use crate::reader::Reader;
+	use crate::writer::Writer;
+	let read = |text: &str| {
+		Reader::new(text.as_bytes())
+			.collect::<Result<Document, _>>()
+			.unwrap()
+	};
+	let write = |document: &Document, width: usize, property_lines: bool| {
+		let mut output = String::new();
+		let mut writer = Writer::new(&mut output);
+		writer.set_width(Some(width));
+		writer.set_property_lines(property_lines);
+		for event in document {
+			writer.push(&event).unwrap();
+		}
+		output
+	};
+	let document = read(
+		"(t)config 1 2 name=example debug=#true {\n    listen host=localhost port=8080 a-very-long-argument-name\n}\nshort a=1",
+	);
+	for (width, property_lines, expected) in [
+		(
+			200,
+			false,
+			"(t)config 1 2 name=example debug=#true {\n    listen host=localhost port=8080 a-very-long-argument-name\n}\nshort a=1",
+		),
+		(
+			24,
+			false,
+			"(t)config 1 2 \\\n          name=example \\\n          debug=#true {\n    listen host=localhost \\\n           port=8080 \\\n           a-very-long-argument-name\n}\nshort a=1",
+		),
+		(
+			60,
+			false,
+			"(t)config 1 2 name=example debug=#true {\n    listen host=localhost port=8080 \\\n           a-very-long-argument-name\n}\nshort a=1",
+		),
+		(
+			60,
+			true,
+			"(t)config 1 2 name=example debug=#true {\n    listen host=localhost \\\n           port=8080 \\\n           a-very-long-argument-name\n}\nshort a=1",
+		),
+	] {
+		let written = write(&document, width, property_lines);
+		assert_eq!(written, expected, "width {width}, {property_lines}");
+		assert_eq!(
+			read(&written),
+			document,
+			"round-trip at width {width}, {property_lines}"
+		);
+	}
+}
+
+#[test]
+fn wrapping_writer_width() {
+	use alloc::string::String;
+
+	use crate::dom::Document;
+	use crate::reader::Reader;
+	use crate::writer::Writer;
+	let text = "(t)config 1 2 name=example debug=#true port=8080 {\n    listen host=localhost port=8080 ab cd ef\n}\nshort a=1 b=2 c=3 d=4 e=5 f=6 g=7 h=8";
+	let document = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	// wide enough for every entry and its ` \`
+	for width in 27..80 {
+		for property_lines in [false, true] {
+			let mut output = String::new();
+			let mut writer = Writer::new(&mut output);
+			writer.set_width(Some(width));
+			writer.set_property_lines(property_lines);
+			for event in &document {
+				writer.push(&event).unwrap();
+			}
+			for line in output.lines() {
+				assert!(
+					line.chars().count() <= width,
+					"width {width}, {property_lines}: {line:?}"
+				);
+			}
+			let reread = Reader::new(output.as_bytes())
+				.collect::<Result<Document, _>>()
+				.unwrap();
+			assert_eq!(
+				reread, document,
+				"round-trip at width {width}, {property_lines}"
+			);
+		}
+	}
+}
+
+#[test]
+fn wrapping_writer_edges() {
+	use alloc::string::String;
+
+	use crate::dom::{Entry, Event, Value};
+	use crate::writer::Writer;
+	let mut output = String::new();
+	let mut writer = Writer::new(&mut output);
+	writer.set_width(Some(13));
+	for event in [
+		Event::Node {
+			r#type: None,
+			name: "node".into(),
+		},
+		Event::Entry(Entry::new_value(Value::String("aaa".into()))),
+		Event::Entry(Entry::new_value(Value::String("bbb".into()))),
+		Event::Children,
+		Event::Node {
+			r#type: None,
+			name: "c".into(),
+		},
+		Event::Entry(Entry::new_prop("a", 1.into())),
+	] {
+		writer.push(&event).unwrap();
+	}
+	// kept entries are still written
+	writer.set_width(None);
+	writer.push(&Event::End).unwrap();
+	writer.push(&Event::End).unwrap();
+	assert_eq!(
+		output, "node aaa \\\n     bbb {\n    c a=1\n}",
+		"` {{` counts towards the width"
+	);
+}
+
 fn test_entry(name: &'static str) {
 	let (input, output) = test_info(name);
 	dom::test(input, output, true);
@@ -36,6 +1419,7 @@ fn test_entry(name: &'static str) {
 mod dom {
 	use std::mem::replace;
 
+	use crate::binary::{Decoder, Encoder};
 	use crate::dom::{Document, Node, Value};
 	use crate::reader::Reader;
 	use crate::validator::Validator;
@@ -78,6 +1462,15 @@ mod dom {
 				.collect::<Result<Document, _>>()
 				.inspect_err(|err| eprintln!("Error: {err:?}"))
 				.ok()?;
+			let mut encoder = Encoder::new();
+			for event in &document {
+				encoder.push(&event);
+			}
+			let decoded = Decoder::new(&encoder.finish())
+				.unwrap()
+				.collect::<Result<Document, _>>()
+				.unwrap();
+			assert_eq!(decoded, document, "Binary mismatch");
 			if normalize {
 				document.normalize();
 				for node in &mut document.nodes {
@@ -109,3 +1502,273 @@ mod dom {
 		);
 	}
 }
+
+#[test]
+fn chunk_input() {
+	use crate::dom::Document;
+	use crate::lexer::ChunkInput;
+	use crate::reader::Reader;
+	let text = "é \"multi-byte ✨\" k=0x10 k=2 {\n\tb #true; c {}\n}\n";
+	// one byte at a time, split across every character
+	let mut rest = text.as_bytes();
+	let input = ChunkInput::new(|buffer: &mut [u8]| {
+		let Some((&byte, tail)) = rest.split_first() else {
+			return Ok(0);
+		};
+		buffer[0] = byte;
+		rest = tail;
+		Ok(1)
+	});
+	let mut chunked = Reader::new(input).collect::<Result<Document, _>>().unwrap();
+	let sliced = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	assert_eq!(chunked, sliced, "same document");
+	chunked.normalize();
+	assert_eq!(
+		chunked.to_string(),
+		"é \"multi-byte ✨\" k=2 {\n    b #true\n    c\n}",
+		"normalized"
+	);
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn fuzz() {
+	use alloc::vec::Vec;
+
+	use arbitrary::{Arbitrary, Unstructured};
+
+	use crate::dom::{Document, Number};
+	use crate::fuzz::{assert_events, assert_number, assert_reread, assert_rewrite};
+	// xorshift, for repeatable inputs
+	let mut state = 0x2545_F491_4F6C_DD1D_u64;
+	let mut bytes = |len: usize| {
+		(0..len)
+			.map(|_| {
+				state ^= state << 13;
+				state ^= state >> 7;
+				state ^= state << 17;
+				state.to_le_bytes()[0]
+			})
+			.collect::<Vec<_>>()
+	};
+	for round in 0..200 {
+		let data = bytes(64 * round);
+		let document = Document::arbitrary(&mut Unstructured::new(&data)).unwrap();
+		assert_events(&document);
+		assert_rewrite(&document);
+		assert_reread(document.to_string().as_bytes());
+		assert_reread(&data);
+		let number = Number::arbitrary(&mut Unstructured::new(&data)).unwrap();
+		assert_number(&number);
+	}
+	for (_, input, _) in spec::TESTS {
+		assert_reread(input.as_bytes());
+	}
+	assert_number(&Number::from(f64::NAN));
+}
+
+#[test]
+fn nul_escape() {
+	use crate::dom::{Document, Entry, Node, Value};
+	use crate::reader::Reader;
+	let mut node = Node::new("a\0");
+	node.entries
+		.push(Entry::new_value(Value::String("b\0c".into())));
+	let text = node.to_string();
+	assert_eq!(text, "\"a\\u{0}\" \"b\\u{0}c\"", "escaped");
+	let document = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	assert_eq!(document.nodes, [node], "round-trip");
+}
+
+#[test]
+fn special_floats() {
+	use crate::dom::Number;
+	assert_eq!(Number::from(f64::NAN), Number::NAN, "nan");
+	assert_eq!(Number::from(f32::NAN), Number::NAN, "f32 nan");
+	assert_eq!(Number::from(f64::INFINITY), Number::INFINITY, "inf");
+	assert_eq!(
+		Number::from(f64::NEG_INFINITY),
+		Number::NEG_INFINITY,
+		"-inf"
+	);
+	assert!(
+		f64::try_from(Number::from(f64::NAN)).is_ok_and(f64::is_nan),
+		"nan round-trip"
+	);
+}
+
+#[test]
+fn short_string_chars() {
+	use crate::dom::Document;
+	use crate::reader::Reader;
+	use crate::ssb2::SmolStrBuilder2;
+	// characters were only written inside a `debug_assert`, so this only
+	// failed in release builds
+	let mut builder = SmolStrBuilder2::new();
+	builder.push('a');
+	builder.push('é');
+	assert_eq!(builder.finish(), "aé", "pushed characters");
+	let document = Reader::new("n \"a\\nb\" 0x1F 1.5".as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	assert_eq!(
+		document.to_string(),
+		"n \"a\\nb\" 0x1F 1.5",
+		"escapes and digits"
+	);
+}
+
+#[test]
+fn transcode_input() {
+	use alloc::vec::Vec;
+
+	use crate::dom::Document;
+	use crate::lexer::{ChunkInput, Encoding, LexerError, TranscodeInput};
+	use crate::reader::{Reader, ReaderError};
+	let text = "é \"multi-byte ✨ 🦀\" k=0x10 {\n\tb #true\n}\n";
+	let expected = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	let le = |utf8: &str| {
+		utf8.encode_utf16()
+			.flat_map(u16::to_le_bytes)
+			.collect::<Vec<_>>()
+	};
+	let be = |utf8: &str| {
+		utf8.encode_utf16()
+			.flat_map(u16::to_be_bytes)
+			.collect::<Vec<_>>()
+	};
+	for (bytes, detected, encoding) in [
+		(le(&["\u{FEFF}", text].concat()), true, Encoding::Utf16Le),
+		(be(&["\u{FEFF}", text].concat()), true, Encoding::Utf16Be),
+		(le(text), false, Encoding::Utf16Le),
+		(
+			["\u{FEFF}", text].concat().into_bytes(),
+			true,
+			Encoding::Utf8,
+		),
+		(text.as_bytes().into(), true, Encoding::Utf8),
+	] {
+		// one byte at a time, split across every code unit
+		let mut rest = &bytes[..];
+		let chunks = ChunkInput::new(|buffer: &mut [u8]| {
+			let Some((&byte, tail)) = rest.split_first() else {
+				return Ok(0);
+			};
+			buffer[0] = byte;
+			rest = tail;
+			Ok(1)
+		});
+		let input = if detected {
+			TranscodeInput::new(chunks)
+		} else {
+			TranscodeInput::with_encoding(chunks, encoding)
+		};
+		let mut reader = Reader::new(input);
+		let document = reader.by_ref().collect::<Result<Document, _>>();
+		assert_eq!(document.unwrap(), expected, "{encoding:?}, {detected}");
+		assert_eq!(
+			reader.input().encoding(),
+			Some(encoding),
+			"{encoding:?}, {detected}"
+		);
+	}
+	let wide = le("\u{FEFF}é ✨🦀 k");
+	let mut wide_reader = Reader::new(TranscodeInput::new(&wide[..]));
+	wide_reader.by_ref().for_each(drop);
+	let offsets = [0, 3, 5, 6, 9, 13, 14].map(|offset| wide_reader.input().source_offset(offset));
+	assert_eq!(offsets, [0, 2, 4, 6, 8, 12, 14], "source offsets");
+	let mut invalid = le("node \"a");
+	invalid.extend([0x00, 0xD8]);
+	invalid.extend(le("\" b"));
+	invalid.push(b'x');
+	let mut reader = Reader::new(TranscodeInput::with_encoding(
+		&invalid[..],
+		Encoding::Utf16Le,
+	));
+	let (err, span) = reader.find_map(Result::err).unwrap();
+	assert!(
+		matches!(err, ReaderError::Lexer(LexerError::InvalidUtf8(7))),
+		"lone surrogate: {err:?}"
+	);
+	assert_eq!(
+		[span.start, 7].map(|offset| reader.input().source_offset(offset)),
+		[10, 14],
+		"lone surrogate at"
+	);
+}
+
+#[test]
+fn transcode_input_large() {
+	use alloc::vec::Vec;
+
+	use crate::lexer::{Encoding, Input, TranscodeInput};
+	let text = ["a".repeat(600), "✨".repeat(600), "é".repeat(600)].concat();
+	let utf16 = text
+		.encode_utf16()
+		.flat_map(u16::to_le_bytes)
+		.collect::<Vec<_>>();
+	let mut input = TranscodeInput::with_encoding(&utf16[..], Encoding::Utf16Le);
+	// more than a single chunk
+	assert_eq!(
+		input.peek(2000).unwrap()[..2000],
+		text.as_bytes()[..2000],
+		"peek"
+	);
+	assert_eq!(input.peek(5000).unwrap(), text.as_bytes(), "peek to end");
+	// interpolated within runs of the same character width
+	let offsets = [0, 600, 603, 2400, 2402, 3600].map(|offset| input.source_offset(offset));
+	assert_eq!(offsets, [0, 1200, 1202, 2400, 2402, 3600], "source offsets");
+}
+
+#[test]
+fn spanned_document() {
+	use crate::dom::spanned::SpannedDocument;
+	use crate::dom::{Document, Value};
+	use crate::reader::Reader;
+	let text = "(t)a 1 key=(u)\"v\" {\n\tb /-c (x)#true\n}\nd";
+	let spanned = SpannedDocument::read(Reader::new(text.as_bytes())).unwrap();
+	let plain = Reader::new(text.as_bytes())
+		.collect::<Result<Document, _>>()
+		.unwrap();
+	assert_eq!(spanned.document, plain, "same document");
+	let (a, a_spans) = spanned.node(&[0]).unwrap();
+	assert_eq!(&text[a_spans.node.clone()], &text[..text.len() - 2], "node");
+	assert_eq!(&text[a_spans.r#type.clone().unwrap()], "(t)", "node type");
+	assert_eq!(&text[a_spans.name.clone()], "a", "node name");
+	let value = a_spans.entry(a, 0).unwrap();
+	assert_eq!(&text[value.entry.clone()], "1", "value entry");
+	assert_eq!(value.name, None, "value name");
+	let prop = a_spans.entry(a, "key").unwrap();
+	assert_eq!(&text[prop.entry.clone()], "key=(u)\"v\"", "prop entry");
+	assert_eq!(&text[prop.name.clone().unwrap()], "key", "prop name");
+	assert_eq!(&text[prop.r#type.clone().unwrap()], "(u)", "prop type");
+	assert_eq!(&text[prop.value.clone()], "\"v\"", "prop value");
+	let (b, b_spans) = spanned.node(&[0, 0]).unwrap();
+	assert_eq!(
+		&text[b_spans.node.clone()],
+		"b /-c (x)#true\n",
+		"child node"
+	);
+	let (entry, entry_spans) = b_spans.entries(b).next().unwrap();
+	assert_eq!(entry.value, Value::Bool(true), "child entry");
+	assert_eq!(&text[entry_spans.entry.clone()], "(x)#true", "child entry");
+	assert_eq!(
+		&text[entry_spans.r#type.clone().unwrap()],
+		"(x)",
+		"child type"
+	);
+	assert_eq!(&text[entry_spans.value.clone()], "#true", "child value");
+	let (_, d_spans) = spanned.node(&[1]).unwrap();
+	assert_eq!(&text[d_spans.node.clone()], "d", "unterminated node");
+	assert!(spanned.node(&[0, 1]).is_none(), "missing node");
+	assert!(
+		SpannedDocument::read(Reader::new(&b"a {"[..])).is_err(),
+		"syntax error"
+	);
+}
@@ -6,11 +6,11 @@
 //!
 //! You probably want to start at [`Validator`].
 
-use std::error::Error;
+use core::error::Error;
 
 use displaydoc::Display;
 
-use crate::dom::Event;
+use crate::dom::{Event, EventRef};
 
 /// Error in validation
 #[derive(Clone, Debug, Display)]
@@ -55,9 +55,12 @@ impl Validator {
 	/// Feed an event into validator.
 	/// # Errors
 	/// Returns any validation errors.
-	pub fn push(&mut self, event: &Event) -> Result<(), ValidatorError> {
-		self.state = match (self.state, event) {
-			(_, Event::End) => {
+	pub fn push<'event, E: Into<EventRef<'event>>>(
+		&mut self,
+		event: E,
+	) -> Result<(), ValidatorError> {
+		self.state = match (self.state, event.into()) {
+			(_, EventRef::End) => {
 				if let Some(nest) = self.nest.checked_sub(1) {
 					self.nest = nest;
 					State::Block
@@ -65,20 +68,20 @@ impl Validator {
 					return Err(ValidatorError::TooManyEnd);
 				}
 			}
-			(State::Node, Event::Entry { .. }) => State::Node,
-			(State::Node, Event::Children) => State::Block,
-			(State::Block, Event::Node { .. }) => {
+			(State::Node, EventRef::Entry(_)) => State::Node,
+			(State::Node, EventRef::Children) => State::Block,
+			(State::Block, EventRef::Node { .. }) => {
 				self.nest += 1;
 				State::Node
 			}
 			(State::Node, event) => {
 				return Err(ValidatorError::Expected(
 					"Entry, Children, or End",
-					event.clone(),
+					event.into(),
 				));
 			}
 			(State::Block, event) => {
-				return Err(ValidatorError::Expected("Node or End", event.clone()));
+				return Err(ValidatorError::Expected("Node or End", event.into()));
 			}
 		};
 		Ok(())
@@ -5,18 +5,87 @@
 
 // TODO: is there much use for a "minified" writer?
 
-use core::fmt;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::error::Error;
+use core::fmt::{self, Write};
+use core::mem::take;
+#[cfg(feature = "std")]
+use std::io::{self, BufWriter};
+
+use displaydoc::Display;
 
 use crate::IdentDisplay;
-use crate::dom::Event;
+use crate::dom::EventRef;
+use crate::validator::{Validator, ValidatorError};
+
+/// An error while writing.
+#[derive(Debug, Display)]
+#[non_exhaustive]
+pub enum WriterError {
+	#[cfg(feature = "std")]
+	#[expect(clippy::absolute_paths, reason = "feature-gated")]
+	/// {0}
+	Io(std::io::Error),
+	#[cfg(not(feature = "std"))]
+	/// IO error
+	Io(()),
+	/// Formatter error
+	Fmt,
+	/// Invalid event stream: {0}
+	Invalid(ValidatorError),
+}
+impl Error for WriterError {}
+impl From<fmt::Error> for WriterError {
+	fn from(_: fmt::Error) -> Self { Self::Fmt }
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 /// Writer of document events.
+///
+/// This trusts its input, an invalid event stream produces invalid output.
+/// Use [`CheckedWriter`] for untrusted event streams.
+///
+/// With a maximum line [width](Self::set_width), entries that don't fit are
+/// continued on the next line with an escline:
+/// ```
+/// # use just_kdl::dom::{Entry, Event};
+/// # use just_kdl::writer::Writer;
+/// let mut output = String::new();
+/// let mut writer = Writer::new(&mut output);
+/// writer.set_width(Some(24));
+/// writer.push(&Event::Node { r#type: None, name: "server".into() })?;
+/// for (key, value) in [("host", "localhost"), ("port", "8080"), ("user", "www")] {
+///     writer.push(&Event::Entry(Entry::new_prop(key, value.into())))?;
+/// }
+/// writer.push(&Event::End)?;
+/// assert_eq!(output, "server host=localhost \\\n       port=\"8080\" \\\n       user=www");
+/// # Ok::<(), core::fmt::Error>(())
+/// ```
 pub struct Writer<W> {
 	inner: W,
 	state: State,
 	indent: usize,
-	indent_text: &'static str,
+	indent_text: Cow<'static, str>,
+	width: Option<usize>,
+	property_lines: bool,
+	/// width of the current node's line before its entries, if its entries
+	/// are wrapped
+	head: Option<usize>,
+	/// entries of the current node and whether they're properties, kept to
+	/// lay them out when wrapping
+	entries: Vec<(bool, String)>,
+}
+
+/// Counts the characters written.
+struct Count(usize);
+
+impl fmt::Write for Count {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		self.0 += s.chars().count();
+		Ok(())
+	}
 }
 
 // since the writer doesn't support comments, there's no need to track a
@@ -36,12 +105,30 @@ impl<W: fmt::Write> Writer<W> {
 			inner: writer,
 			state: State::First,
 			indent: 0,
-			indent_text: "    ",
+			indent_text: Cow::Borrowed("    "),
+			width: None,
+			property_lines: false,
+			head: None,
+			entries: Vec::new(),
 		}
 	}
 	/// Change the text inserted for each indentation level,
 	/// default is four spaces
-	pub fn set_indent(&mut self, indent: &'static str) { self.indent_text = indent; }
+	pub fn set_indent<I: Into<Cow<'static, str>>>(&mut self, indent: I) {
+		self.indent_text = indent.into();
+	}
+	/// Change the maximum line width in characters, past which entries are
+	/// continued on the next line, aligned with the node's first entry,
+	/// default is `None` for no limit.
+	///
+	/// Lines can still be longer, if a single entry doesn't fit. Changes apply
+	/// from the next node.
+	pub fn set_width(&mut self, width: Option<usize>) { self.width = width; }
+	/// Put each property on its own line when a node's entries are wrapped,
+	/// default is `false`.
+	pub fn set_property_lines(&mut self, property_lines: bool) {
+		self.property_lines = property_lines;
+	}
 	fn line(&mut self) -> fmt::Result {
 		writeln!(self.inner)?;
 		for _ in 0..self.indent {
@@ -52,9 +139,9 @@ impl<W: fmt::Write> Writer<W> {
 	/// Write an event to the writer
 	/// # Errors
 	/// If the inner writer errors
-	pub fn push(&mut self, event: &Event) -> fmt::Result {
-		match event {
-			Event::Node { r#type, name } => {
+	pub fn push<'event, E: Into<EventRef<'event>>>(&mut self, event: E) -> fmt::Result {
+		match event.into() {
+			EventRef::Node { r#type, name } => {
 				if self.state != State::First {
 					self.line()?;
 				}
@@ -62,22 +149,39 @@ impl<W: fmt::Write> Writer<W> {
 				if let Some(r#type) = r#type {
 					write!(self.inner, "({})", IdentDisplay(r#type))?;
 				}
+				if self.width.is_some() {
+					let mut count = Count(self.indent * self.indent_text.chars().count());
+					if let Some(r#type) = r#type {
+						write!(count, "({})", IdentDisplay(r#type))?;
+					}
+					write!(count, "{}", IdentDisplay(name))?;
+					self.head = Some(count.0);
+				}
 				write!(self.inner, "{}", IdentDisplay(name))
 			}
-			Event::Entry(entry) => write!(self.inner, " {entry}"),
-			Event::Children => {
+			EventRef::Entry(entry) if self.head.is_some() => {
+				let mut text = String::new();
+				write!(text, "{entry}")?;
+				self.entries.push((entry.name.is_some(), text));
+				Ok(())
+			}
+			EventRef::Entry(entry) => write!(self.inner, " {entry}"),
+			EventRef::Children => {
+				// fit the ` {` too
+				self.write_entries(2)?;
 				self.indent += 1;
 				self.state = State::BlockStart;
 				write!(self.inner, " {{")
 			}
-			Event::End => {
+			EventRef::End => {
+				self.write_entries(0)?;
 				match self.state {
 					State::BlockStart => {
-						self.indent -= 1;
+						self.indent = self.indent.saturating_sub(1);
 						write!(self.inner, "}}")?;
 					}
 					State::Block => {
-						self.indent -= 1;
+						self.indent = self.indent.saturating_sub(1);
 						self.line()?;
 						write!(self.inner, "}}")?;
 					}
@@ -88,9 +192,109 @@ impl<W: fmt::Write> Writer<W> {
 			}
 		}
 	}
+	/// write the kept entries of the current node, wrapping them as needed,
+	/// with `tail` characters after them on the line
+	fn write_entries(&mut self, tail: usize) -> fmt::Result {
+		let Some(head) = self.head.take() else {
+			return Ok(());
+		};
+		// the width can be unset after the entries were kept
+		let width = self.width.unwrap_or(usize::MAX);
+		let mut entries = take(&mut self.entries);
+		let mut lengths = entries
+			.iter()
+			.map(|(_, text)| text.chars().count())
+			.collect::<Vec<_>>();
+		if let Some(last) = lengths.last_mut() {
+			*last += tail;
+		}
+		// a single line has no ` \` to make room for
+		let wrap = head + lengths.iter().map(|len| len + 1).sum::<usize>() > width;
+		let indent_width = self.indent * self.indent_text.chars().count();
+		let mut column = head;
+		let mut previous = None;
+		let count = entries.len();
+		for (index, ((property, text), len)) in entries.iter().zip(lengths).enumerate() {
+			if let Some(previous_property) = previous {
+				let own_line = self.property_lines && (*property || previous_property);
+				// room for the ` \` if the line is continued after this entry
+				let continued = if index + 1 < count { 2 } else { 0 };
+				if wrap && (column + 1 + len + continued > width || own_line) {
+					self.inner.write_str(" \\")?;
+					self.line()?;
+					write!(self.inner, "{:1$}", "", head - indent_width)?;
+					column = head;
+				}
+			}
+			write!(self.inner, " {text}")?;
+			column += 1 + len;
+			previous = Some(*property);
+		}
+		// keep the allocation
+		entries.clear();
+		self.entries = entries;
+		Ok(())
+	}
+}
+
+/// [`Writer`] that rejects invalid event streams, using a [`Validator`].
+///
+/// ```
+/// # use just_kdl::dom::Event;
+/// # use just_kdl::writer::{CheckedWriter, WriterError};
+/// let mut writer = CheckedWriter::new(String::new());
+/// writer.push(&Event::Node { r#type: None, name: "node".into() })?;
+/// assert!(matches!(writer.push(&Event::Node { r#type: None, name: "child".into() }), Err(WriterError::Invalid(_))));
+/// writer.push(&Event::Children)?;
+/// assert!(matches!(writer.finish(), Err(WriterError::Invalid(_))));
+/// # Ok::<(), WriterError>(())
+/// ```
+#[derive(Debug)]
+pub struct CheckedWriter<W> {
+	writer: Writer<W>,
+	validator: Validator,
+}
+
+impl<W: fmt::Write> CheckedWriter<W> {
+	/// Create a new writer with an output.
+	pub const fn new(writer: W) -> Self {
+		Self {
+			writer: Writer::new(writer),
+			validator: Validator::new(),
+		}
+	}
+	/// Change the text inserted for each indentation level,
+	/// default is four spaces
+	pub fn set_indent<I: Into<Cow<'static, str>>>(&mut self, indent: I) {
+		self.writer.set_indent(indent);
+	}
+	/// Change the maximum line width, see [`Writer::set_width`].
+	pub fn set_width(&mut self, width: Option<usize>) { self.writer.set_width(width); }
+	/// Put each property on its own line when wrapping, see
+	/// [`Writer::set_property_lines`].
+	pub fn set_property_lines(&mut self, property_lines: bool) {
+		self.writer.set_property_lines(property_lines);
+	}
+	/// Write an event to the writer, invalid events aren't written.
+	/// # Errors
+	/// If the event is out of order, or the inner writer errors
+	pub fn push<'event, E: Into<EventRef<'event>>>(&mut self, event: E) -> Result<(), WriterError> {
+		let event = event.into();
+		self.validator.push(event).map_err(WriterError::Invalid)?;
+		Ok(self.writer.push(event)?)
+	}
+	/// Mark the end of the event stream, and return the output.
+	/// # Errors
+	/// If any nodes are unclosed
+	pub fn finish(self) -> Result<W, WriterError> {
+		self.validator.done().map_err(WriterError::Invalid)?;
+		Ok(self.writer.inner)
+	}
 }
 
 /// Bridge from [`fmt::Write`] to [`std::io::Write`], for writing to IO streams.
+///
+/// This loses the cause of IO errors, see [`IoWriter`] instead.
 #[cfg(feature = "std")]
 #[cfg_attr(docsrs, doc(cfg(feature = "std")))]
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -103,3 +307,106 @@ impl<T: std::io::Write> fmt::Write for WriteOutput<T> {
 		self.0.write_all(s.as_bytes()).map_err(|_| fmt::Error)
 	}
 }
+
+/// Buffered output that keeps the first IO error.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct IoOutput<T: io::Write> {
+	inner: BufWriter<T>,
+	error: Option<io::Error>,
+}
+
+#[cfg(feature = "std")]
+impl<T: io::Write> fmt::Write for IoOutput<T> {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		io::Write::write_all(&mut self.inner, s.as_bytes()).map_err(|err| {
+			self.error = Some(err);
+			fmt::Error
+		})
+	}
+}
+
+/// Writer of document events to a [`std::io::Write`], with buffering and IO
+/// error reporting.
+///
+/// ```
+/// # use just_kdl::dom::Event;
+/// # use just_kdl::writer::IoWriter;
+/// let mut writer = IoWriter::new(Vec::new());
+/// writer.push(&Event::Node { r#type: None, name: "node".into() })?;
+/// writer.push(&Event::End)?;
+/// assert_eq!(writer.finish()?, b"node");
+/// # Ok::<(), just_kdl::writer::WriterError>(())
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(docsrs, doc(cfg(feature = "std")))]
+#[derive(Debug)]
+pub struct IoWriter<T: io::Write> {
+	writer: Writer<IoOutput<T>>,
+	validator: Option<Validator>,
+}
+
+#[cfg(feature = "std")]
+impl<T: io::Write> IoWriter<T> {
+	/// Create a new writer with an output.
+	pub fn new(output: T) -> Self {
+		Self {
+			writer: Writer::new(IoOutput {
+				inner: BufWriter::new(output),
+				error: None,
+			}),
+			validator: None,
+		}
+	}
+	/// Reject invalid event streams like [`CheckedWriter`], must be set
+	/// before any events are written.
+	pub fn set_checked(&mut self, checked: bool) { self.validator = checked.then(Validator::new); }
+	/// Change the text inserted for each indentation level,
+	/// default is four spaces
+	pub fn set_indent<I: Into<Cow<'static, str>>>(&mut self, indent: I) {
+		self.writer.set_indent(indent);
+	}
+	/// Change the maximum line width, see [`Writer::set_width`].
+	pub fn set_width(&mut self, width: Option<usize>) { self.writer.set_width(width); }
+	/// Put each property on its own line when wrapping, see
+	/// [`Writer::set_property_lines`].
+	pub fn set_property_lines(&mut self, property_lines: bool) {
+		self.writer.set_property_lines(property_lines);
+	}
+	fn output_error(&mut self) -> WriterError {
+		self.writer
+			.inner
+			.error
+			.take()
+			.map_or(WriterError::Fmt, WriterError::Io)
+	}
+	/// Write an event to the writer
+	/// # Errors
+	/// If the output errors, or the event is out of order in checked mode
+	pub fn push<'event, E: Into<EventRef<'event>>>(&mut self, event: E) -> Result<(), WriterError> {
+		let event = event.into();
+		if let Some(validator) = &mut self.validator {
+			validator.push(event).map_err(WriterError::Invalid)?;
+		}
+		self.writer.push(event).map_err(|_| self.output_error())
+	}
+	/// Write any buffered text to the output.
+	/// # Errors
+	/// If the output errors
+	pub fn flush(&mut self) -> Result<(), WriterError> {
+		io::Write::flush(&mut self.writer.inner.inner).map_err(WriterError::Io)
+	}
+	/// Flush and return the output.
+	/// # Errors
+	/// If the output errors, or any nodes are unclosed in checked mode
+	pub fn finish(self) -> Result<T, WriterError> {
+		if let Some(validator) = self.validator {
+			validator.done().map_err(WriterError::Invalid)?;
+		}
+		self.writer
+			.inner
+			.inner
+			.into_inner()
+			.map_err(|err| WriterError::Io(err.into_error()))
+	}
+}